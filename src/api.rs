@@ -1,6 +1,20 @@
 use reqwest::blocking::Client;
 use serde::{Deserialize, Serialize};
 
+/// Why a location or the weather couldn't be fetched.
+pub enum FetchError {
+    /// Geocoding found no place of that name
+    UnknownLocation(String),
+    /// The request failed or its answer couldn't be used
+    Failed(String),
+}
+
+impl From<String> for FetchError {
+    fn from(message: String) -> Self {
+        Self::Failed(message)
+    }
+}
+
 #[derive(Debug)]
 pub struct ResolvedLocation {
     pub lat: f64,
@@ -15,6 +29,8 @@ pub struct WeatherData {
     #[serde(default)]
     pub hourly: Option<HourlyForecast>,
     pub timezone: String,
+    #[serde(default)]
+    pub utc_offset_seconds: i32,
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -31,6 +47,8 @@ pub struct CurrentWeather {
     #[serde(default)]
     pub wind_direction_10m: Option<f64>,
     #[serde(default)]
+    pub wind_gusts_10m: Option<f64>,
+    #[serde(default)]
     pub pressure_msl: Option<f64>,
     #[serde(default)]
     pub precipitation: Option<f64>,
@@ -57,6 +75,8 @@ pub struct HourlyForecast {
     pub weather_code: Vec<u8>,
     #[serde(default)]
     pub precipitation_probability: Vec<u8>,
+    #[serde(default)]
+    pub is_day: Vec<u8>,
 }
 
 #[derive(Deserialize)]
@@ -79,7 +99,7 @@ struct IpApiResponse {
     city: String,
 }
 
-pub fn geocode(client: &Client, city: &str) -> Result<ResolvedLocation, String> {
+pub fn geocode(client: &Client, city: &str) -> Result<ResolvedLocation, FetchError> {
     let url = format!(
         "https://geocoding-api.open-meteo.com/v1/search?name={}&count=1",
         urlencoding(city)
@@ -93,11 +113,10 @@ pub fn geocode(client: &Client, city: &str) -> Result<ResolvedLocation, String>
         .json()
         .map_err(|e| format!("geocoding parse failed: {e}"))?;
 
-    let result = resp
-        .results
-        .into_iter()
-        .next()
-        .ok_or_else(|| format!("no results for location '{city}'"))?;
+    let result =
+        resp.results.into_iter().next().ok_or_else(|| {
+            FetchError::UnknownLocation(format!("no results for location '{city}'"))
+        })?;
 
     Ok(ResolvedLocation {
         lat: result.latitude,
@@ -128,20 +147,14 @@ pub fn geolocate_ip(client: &Client) -> Result<ResolvedLocation, String> {
     })
 }
 
-pub enum Units {
-    Metric,
-    Imperial,
-}
-
 pub fn fetch_weather(
     client: &Client,
     lat: f64,
     lon: f64,
     days: u8,
     hours: u8,
-    units: &Units,
 ) -> Result<WeatherData, String> {
-    let current_params = "temperature_2m,relative_humidity_2m,apparent_temperature,weather_code,is_day,wind_speed_10m,wind_direction_10m,pressure_msl,precipitation";
+    let current_params = "temperature_2m,relative_humidity_2m,apparent_temperature,weather_code,is_day,wind_speed_10m,wind_direction_10m,wind_gusts_10m,pressure_msl,precipitation";
     let daily_params = "weather_code,temperature_2m_max,temperature_2m_min,sunrise,sunset,precipitation_probability_max,wind_speed_10m_max";
 
     let mut url = format!(
@@ -149,14 +162,7 @@ pub fn fetch_weather(
     );
 
     if hours > 0 {
-        url.push_str("&hourly=temperature_2m,weather_code,precipitation_probability");
-    }
-
-    match units {
-        Units::Imperial => {
-            url.push_str("&temperature_unit=fahrenheit&wind_speed_unit=mph");
-        }
-        Units::Metric => {}
+        url.push_str("&hourly=temperature_2m,weather_code,precipitation_probability,is_day");
     }
 
     let data: WeatherData = client
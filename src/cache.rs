@@ -1,13 +1,20 @@
 use std::fs;
-use std::io::Write;
+use std::io::{ErrorKind, Write};
 use std::path::Path;
 
 use serde::{Deserialize, Serialize};
 
 use crate::api::WeatherData;
 
+/// Bumped whenever the stored weather changes meaning. Entries from before
+/// version 2 may hold imperial values, which would be converted twice.
+pub const VERSION: u32 = 2;
+
 #[derive(Serialize, Deserialize)]
 pub struct CacheEntry {
+    /// Missing in entries written before versioning
+    #[serde(default)]
+    pub version: u32,
     pub weather: WeatherData,
     pub city: String,
     pub location_query: Option<String>,
@@ -37,12 +44,19 @@ pub fn save(entry: &CacheEntry, cache_dir: &Path) -> Result<(), String> {
     Ok(())
 }
 
-pub fn load(cache_dir: &Path) -> Result<CacheEntry, String> {
+/// The cached weather, or `None` if nothing was cached yet.
+pub fn load(cache_dir: &Path) -> Result<Option<CacheEntry>, String> {
     let path = cache_dir.join("last.json");
-    let contents = fs::read_to_string(&path).map_err(|e| format!("failed to read cache: {e}"))?;
+    let contents = match fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(format!("failed to read cache: {e}")),
+    };
 
     match serde_json::from_str::<CacheEntry>(&contents) {
-        Ok(entry) => Ok(entry),
+        Ok(entry) if entry.version == VERSION => Ok(Some(entry)),
+        // Overwritten by the next successful fetch
+        Ok(_) => Err("cache from another meteobar version (ignored)".to_string()),
         Err(e) => {
             let _ = fs::remove_file(&path);
             Err(format!("malformed cache (deleted): {e}"))
@@ -51,7 +65,7 @@ pub fn load(cache_dir: &Path) -> Result<CacheEntry, String> {
 }
 
 pub fn get_cached_location(cache_dir: &Path, location_query: &str) -> Option<(f64, f64)> {
-    let entry = load(cache_dir).ok()?;
+    let entry = load(cache_dir).ok().flatten()?;
     let cached_query = entry.location_query.as_deref()?;
     if cached_query.to_lowercase() == location_query.to_lowercase() {
         Some((entry.lat, entry.lon))
@@ -0,0 +1,154 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::theme;
+
+/// `~/.config/meteobar/config.toml`
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct Config {
+    /// Tooltip color roles, either overriding a theme role or adding a new one
+    pub colors: BTreeMap<String, String>,
+    /// Temperature (°C) to color, a hex value or a role name
+    pub temp_gradient: BTreeMap<String, String>,
+    pub polybar: BarConfig,
+    pub i3bar: BarConfig,
+    pub tmux: BarConfig,
+    pub xmobar: ActionBarConfig,
+    pub dzen2: ActionBarConfig,
+}
+
+/// Settings of a bar without CSS, under the bar's own table.
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct BarConfig {
+    /// CSS class (`rainy`, `stale`, ...) to the color of the whole bar text
+    pub class_colors: BTreeMap<String, String>,
+}
+
+impl BarConfig {
+    /// `class_colors` as `#rrggbb`; `section` names the table in errors.
+    pub fn class_colors(&self, section: &str) -> Result<BTreeMap<String, String>, String> {
+        self.class_colors
+            .iter()
+            .map(|(class, value)| match theme::normalize(value) {
+                Some(color) => Ok((class.clone(), color)),
+                None => Err(format!(
+                    "{section}.class_colors.{class}: '{value}' is not a color"
+                )),
+            })
+            .collect()
+    }
+}
+
+/// A bar that can also run commands on clicks and switch fonts.
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct ActionBarConfig {
+    #[serde(flatten)]
+    pub bar: BarConfig,
+    /// Mouse button (1-5) to the shell command it runs
+    pub actions: BTreeMap<String, String>,
+    /// Font for icons whose set needs one of its own
+    pub icon_font: Option<FontRef>,
+}
+
+/// xmobar refers to its fonts by index, dzen2 by name.
+#[derive(Deserialize, Clone)]
+#[serde(untagged)]
+pub enum FontRef {
+    Index(u8),
+    Name(String),
+}
+
+impl ActionBarConfig {
+    /// `actions` by button; `section` names the table in errors.
+    pub fn actions(&self, section: &str) -> Result<Vec<(u8, String)>, String> {
+        self.actions
+            .iter()
+            .map(|(button, command)| match button.parse() {
+                Ok(n @ 1..=5) => Ok((n, command.clone())),
+                _ => Err(format!(
+                    "{section}.actions: '{button}' is not a mouse button 1-5"
+                )),
+            })
+            .collect()
+    }
+}
+
+impl Config {
+    /// An explicit `path` must exist; the default location is optional.
+    pub fn load(path: Option<&Path>) -> Result<Self, String> {
+        let (path, required) = match path {
+            Some(p) => (p.to_path_buf(), true),
+            None => match default_path() {
+                Some(p) => (p, false),
+                None => return Ok(Self::default()),
+            },
+        };
+
+        let content = match fs::read_to_string(&path) {
+            Ok(c) => c,
+            Err(_) if !required && !path.exists() => return Ok(Self::default()),
+            Err(e) => return Err(format!("failed to read config {}: {e}", path.display())),
+        };
+        toml::from_str(&content).map_err(|e| format!("config {}: {e}", path.display()))
+    }
+}
+
+/// The file `load` reads, for watching.
+pub fn path(path: Option<&Path>) -> Option<PathBuf> {
+    path.map(Path::to_path_buf).or_else(default_path)
+}
+
+fn default_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|d| d.join("meteobar/config.toml"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn xmobar(toml: &str) -> ActionBarConfig {
+        toml::from_str::<Config>(toml).unwrap().xmobar
+    }
+
+    #[test]
+    fn actions_are_ordered_by_button() {
+        let config = xmobar("[xmobar.actions]\n3 = \"pavucontrol\"\n1 = \"xdg-open x\"\n");
+        assert_eq!(
+            config.actions("xmobar").unwrap(),
+            [
+                (1, "xdg-open x".to_string()),
+                (3, "pavucontrol".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn actions_reject_buttons_outside_1_to_5() {
+        for button in ["0", "6", "left", "-1"] {
+            let config = xmobar(&format!("[xmobar.actions]\n{button} = \"true\"\n"));
+            let err = config.actions("xmobar").unwrap_err();
+            assert_eq!(
+                err,
+                format!("xmobar.actions: '{button}' is not a mouse button 1-5")
+            );
+        }
+    }
+
+    #[test]
+    fn action_bars_share_class_colors_and_take_either_font_ref() {
+        let config = xmobar("[xmobar]\nicon_font = 2\n[xmobar.class_colors]\nrainy = \"#abc\"\n");
+        assert!(matches!(config.icon_font, Some(FontRef::Index(2))));
+        assert_eq!(
+            config.bar.class_colors("xmobar").unwrap()["rainy"],
+            "#aabbcc"
+        );
+        let config = xmobar("[xmobar]\nicon_font = \"Font Awesome\"\n");
+        assert!(matches!(config.icon_font, Some(FontRef::Name(name)) if name == "Font Awesome"));
+    }
+}
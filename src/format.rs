@@ -1,19 +1,242 @@
+use std::borrow::Cow;
+use std::fmt::Write;
+use std::str::FromStr;
+
+use chrono::{NaiveDate, NaiveDateTime, Timelike};
+
+use crate::api::WeatherData;
+use crate::i18n::Locale;
+use crate::icons::{get_icon, IconSet};
+use crate::output::Renderer;
+use crate::theme::ThemeColors;
+use crate::units::Units;
+
 pub struct FormatData {
     pub icon: String,
+    /// `icon` without markup
+    pub icon_glyph: String,
+    /// Cells `icon` takes, when its set declares it
+    pub icon_width: Option<usize>,
     pub temp: String,
+    /// Gradient color of the current temperature
+    pub temp_color: String,
     pub feels_like: String,
     pub humidity: String,
     pub wind: String,
     pub wind_dir: String,
+    pub wind_arrow: String,
+    pub gusts: String,
+    pub beaufort: String,
+    pub beaufort_desc: String,
     pub pressure: String,
+    pub precipitation: String,
     pub city: String,
     pub min: String,
     pub max: String,
     pub rain_chance: String,
     pub description: String,
+    pub sunrise: String,
+    pub sunset: String,
+    pub unit: String,
+    pub speed_unit: String,
+    pub pressure_unit: String,
+    pub precip_unit: String,
+}
+
+/// Settings that shape placeholder values besides the weather itself.
+pub struct FormatOptions<'a> {
+    pub icon_set: &'a IconSet,
+    pub locale: &'a Locale,
+    pub units: &'a Units,
+    pub time_format: &'a TimeFormat,
+    pub wind_arrow: WindArrow,
+    pub colors: &'a ThemeColors,
+}
+
+impl FormatData {
+    pub fn new(weather: &WeatherData, city: &str, opts: &FormatOptions) -> Self {
+        let (locale, units, time_format) = (opts.locale, opts.units, opts.time_format);
+        let current = &weather.current;
+        let temp = current.temperature_2m.round() as i32;
+        let wind_speed = current.wind_speed_10m.unwrap_or(0.0);
+        let wind_deg = current.wind_direction_10m.unwrap_or(0.0);
+        let force = units.wind.beaufort(wind_speed);
+        let icon_info = get_icon(current.weather_code, current.is_day == 1, opts.icon_set);
+        let description = locale.description(&icon_info).to_string();
+        let today_rain = weather
+            .daily
+            .precipitation_probability_max
+            .first()
+            .copied()
+            .unwrap_or(0);
+
+        Self {
+            icon: icon_info.icon,
+            icon_glyph: icon_info.glyph,
+            icon_width: icon_info.width,
+            temp: temp.to_string(),
+            temp_color: opts.colors.temp_color(current.temperature_2m),
+            feels_like: format!(
+                "{}",
+                current
+                    .apparent_temperature
+                    .unwrap_or(current.temperature_2m)
+                    .round() as i32
+            ),
+            humidity: format!(
+                "{}",
+                current.relative_humidity_2m.unwrap_or(0.0).round() as i32
+            ),
+            wind: units.wind.format(wind_speed),
+            wind_dir: degrees_to_cardinal(wind_deg).to_string(),
+            wind_arrow: opts.wind_arrow.glyph(wind_deg).to_string(),
+            gusts: current
+                .wind_gusts_10m
+                .map(|g| units.wind.format(g))
+                .unwrap_or_default(),
+            beaufort: force.to_string(),
+            beaufort_desc: locale.beaufort(force).to_string(),
+            pressure: units.pressure.format(current.pressure_msl.unwrap_or(0.0)),
+            precipitation: units.precip.format(current.precipitation.unwrap_or(0.0)),
+            city: city.to_string(),
+            min: format!(
+                "{}",
+                weather
+                    .daily
+                    .temperature_2m_min
+                    .first()
+                    .unwrap_or(&0.0)
+                    .round() as i32
+            ),
+            max: format!(
+                "{}",
+                weather
+                    .daily
+                    .temperature_2m_max
+                    .first()
+                    .unwrap_or(&0.0)
+                    .round() as i32
+            ),
+            rain_chance: format!("{}", today_rain),
+            description,
+            sunrise: weather
+                .daily
+                .sunrise
+                .first()
+                .map(|t| time_format.format(t))
+                .unwrap_or_default(),
+            sunset: weather
+                .daily
+                .sunset
+                .first()
+                .map(|t| time_format.format(t))
+                .unwrap_or_default(),
+            unit: units.temp.label().to_string(),
+            speed_unit: units.wind.label().to_string(),
+            pressure_unit: units.pressure.label().to_string(),
+            precip_unit: units.precip.label().to_string(),
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        match key {
+            "icon" => Some(&self.icon),
+            "temp" => Some(&self.temp),
+            "temp_colored" => Some(&self.temp),
+            "feels_like" => Some(&self.feels_like),
+            "humidity" => Some(&self.humidity),
+            "wind" => Some(&self.wind),
+            "wind_dir" => Some(&self.wind_dir),
+            "wind_arrow" => Some(&self.wind_arrow),
+            "gusts" => Some(&self.gusts),
+            "beaufort" => Some(&self.beaufort),
+            "beaufort_desc" => Some(&self.beaufort_desc),
+            "pressure" => Some(&self.pressure),
+            "precipitation" => Some(&self.precipitation),
+            "city" => Some(&self.city),
+            "min" => Some(&self.min),
+            "max" => Some(&self.max),
+            "rain_chance" => Some(&self.rain_chance),
+            "description" => Some(&self.description),
+            "sunrise" => Some(&self.sunrise),
+            "sunset" => Some(&self.sunset),
+            "unit" => Some(&self.unit),
+            "speed_unit" => Some(&self.speed_unit),
+            "pressure_unit" => Some(&self.pressure_unit),
+            "precip_unit" => Some(&self.precip_unit),
+            _ => None,
+        }
+    }
+
+    /// Value of `key` in the renderer's markup. Icons and `temp_colored` get
+    /// the markup they need; everything else is plain text and gets escaped.
+    pub fn markup(&self, key: &str, renderer: &dyn Renderer) -> Option<String> {
+        match key {
+            "icon" => Some(renderer.icon(&self.icon, &self.icon_glyph)),
+            "temp_colored" => Some(renderer.colored(&self.temp_color, &self.temp)),
+            _ => self.get(key).map(|v| renderer.escape(v)),
+        }
+    }
+}
+
+/// How clock times (hourly rows, sunrise/sunset) are shown.
+#[derive(Clone)]
+pub enum TimeFormat {
+    TwentyFourHour,
+    /// `3 PM`, or `6:42 AM` when the minutes are not zero
+    TwelveHour,
+    Custom(String),
+}
+
+impl FromStr for TimeFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "24h" => Ok(Self::TwentyFourHour),
+            "12h" => Ok(Self::TwelveHour),
+            _ => {
+                // chrono panics at format time on invalid specifiers and on
+                // `%z`/`%Z`, which a local time without an offset cannot fill
+                let sample = NaiveDate::from_ymd_opt(2024, 5, 14)
+                    .and_then(|d| d.and_hms_opt(15, 42, 0))
+                    .expect("valid sample time");
+                let mut out = String::new();
+                write!(out, "{}", sample.format(s)).map_err(|_| {
+                    format!("invalid time format '{s}' (time zones are not available)")
+                })?;
+                Ok(Self::Custom(s.to_string()))
+            }
+        }
+    }
+}
+
+impl TimeFormat {
+    /// Formats an Open-Meteo local timestamp (`2024-05-14T15:00`).
+    pub fn format(&self, timestamp: &str) -> String {
+        let Ok(time) = NaiveDateTime::parse_from_str(timestamp, "%Y-%m-%dT%H:%M") else {
+            return "??:??".to_string();
+        };
+        let fmt = match self {
+            Self::TwentyFourHour => "%H:%M",
+            Self::TwelveHour if time.minute() == 0 => "%-I %p",
+            Self::TwelveHour => "%-I:%M %p",
+            Self::Custom(fmt) => fmt,
+        };
+        time.format(fmt).to_string()
+    }
 }
 
-pub fn render(template: &str, data: &FormatData) -> String {
+pub fn render(template: &str, data: &FormatData, renderer: &dyn Renderer) -> String {
+    render_with(template, |key| data.markup(key, renderer).map(Cow::Owned))
+}
+
+/// Expands `{key}` placeholders using `resolve`. Unknown keys and unclosed
+/// braces are left in the output verbatim.
+pub fn render_with<'a, F>(template: &str, resolve: F) -> String
+where
+    F: Fn(&str) -> Option<Cow<'a, str>>,
+{
     let mut result = String::with_capacity(template.len());
     let mut chars = template.chars().peekable();
 
@@ -29,8 +252,8 @@ pub fn render(template: &str, data: &FormatData) -> String {
                 key.push(inner);
             }
             if found_close {
-                match resolve_placeholder(&key, data) {
-                    Some(val) => result.push_str(val),
+                match resolve(&key) {
+                    Some(val) => result.push_str(&val),
                     None => {
                         result.push('{');
                         result.push_str(&key);
@@ -49,26 +272,52 @@ pub fn render(template: &str, data: &FormatData) -> String {
     result
 }
 
-fn resolve_placeholder<'a>(key: &str, data: &'a FormatData) -> Option<&'a str> {
-    match key {
-        "icon" => Some(&data.icon),
-        "temp" => Some(&data.temp),
-        "feels_like" => Some(&data.feels_like),
-        "humidity" => Some(&data.humidity),
-        "wind" => Some(&data.wind),
-        "wind_dir" => Some(&data.wind_dir),
-        "pressure" => Some(&data.pressure),
-        "city" => Some(&data.city),
-        "min" => Some(&data.min),
-        "max" => Some(&data.max),
-        "rain_chance" => Some(&data.rain_chance),
-        "description" => Some(&data.description),
-        _ => None,
+pub fn degrees_to_cardinal(degrees: f64) -> &'static str {
+    let dirs = [
+        "N", "NNE", "NE", "ENE", "E", "ESE", "SE", "SSE", "S", "SSW", "SW", "WSW", "W", "WNW",
+        "NW", "NNW",
+    ];
+    let index = ((degrees.rem_euclid(360.0) + 11.25) / 22.5) as usize % 16;
+    dirs[index]
+}
+
+/// Which way `{wind_arrow}` points. Reported wind directions are where the
+/// wind comes from.
+#[derive(Clone, Copy, clap::ValueEnum)]
+pub enum WindArrow {
+    From,
+    To,
+}
+
+impl WindArrow {
+    pub fn glyph(self, degrees: f64) -> &'static str {
+        let arrows = ["↑", "↗", "→", "↘", "↓", "↙", "←", "↖"];
+        let bearing = match self {
+            Self::From => degrees,
+            Self::To => degrees + 180.0,
+        };
+        let index = ((bearing.rem_euclid(360.0) + 22.5) / 45.0) as usize % 8;
+        arrows[index]
     }
 }
 
-pub fn degrees_to_cardinal(degrees: f64) -> &'static str {
-    let dirs = ["N", "NE", "E", "SE", "S", "SW", "W", "NW"];
-    let index = ((degrees + 22.5) / 45.0) as usize % 8;
-    dirs[index]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn time_format_presets_and_custom() {
+        let time = "2024-05-14T15:00";
+        assert_eq!("24h".parse::<TimeFormat>().unwrap().format(time), "15:00");
+        assert_eq!("12h".parse::<TimeFormat>().unwrap().format(time), "3 PM");
+        let custom: TimeFormat = "%Hh%M".parse().unwrap();
+        assert_eq!(custom.format("2024-05-14T06:42"), "06h42");
+    }
+
+    #[test]
+    fn time_format_rejects_time_zones_and_invalid_specifiers() {
+        for fmt in ["%H:%M %Z", "%H:%M %z", "%:z", "%Q", "%"] {
+            assert!(fmt.parse::<TimeFormat>().is_err(), "{fmt} was accepted");
+        }
+    }
 }
@@ -0,0 +1,256 @@
+use std::collections::HashMap;
+use std::env;
+use std::fmt::Write;
+use std::fs;
+
+use chrono::{Datelike, NaiveDate, Weekday};
+use serde::Deserialize;
+
+use crate::icons::IconInfo;
+use crate::log;
+
+// Catalogs shipped in the binary. Files in ~/.config/meteobar/locales/
+// with the same name take precedence, key by key.
+const BUILTIN: &[(&str, &str)] = &[
+    ("en", include_str!("../locales/en.toml")),
+    ("es", include_str!("../locales/es.toml")),
+    ("de", include_str!("../locales/de.toml")),
+    ("ja", include_str!("../locales/ja.toml")),
+];
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct Catalog {
+    ui: HashMap<String, String>,
+    days: Names,
+    months: Names,
+    beaufort: BeaufortNames,
+    weather: HashMap<String, String>,
+}
+
+/// Abbreviated and full names of weekdays or months.
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct Names {
+    short: Vec<String>,
+    long: Vec<String>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct BeaufortNames {
+    names: Vec<String>,
+}
+
+pub struct Locale {
+    // Most specific first, English last
+    catalogs: Vec<Catalog>,
+}
+
+impl Locale {
+    pub fn load(lang: Option<&str>) -> Self {
+        let lang = lang.map(str::to_string).unwrap_or_else(lang_from_env);
+        let override_dir = dirs::config_dir().map(|d| d.join("meteobar/locales"));
+
+        let mut catalogs = Vec::new();
+        for name in candidates(&lang) {
+            if let Some(dir) = &override_dir {
+                let path = dir.join(format!("{name}.toml"));
+                if let Ok(content) = fs::read_to_string(&path) {
+                    match toml::from_str(&content) {
+                        Ok(catalog) => catalogs.push(catalog),
+                        Err(e) => log::debug(format_args!("ignoring {}: {e}", path.display())),
+                    }
+                }
+            }
+            if let Some((_, content)) = BUILTIN.iter().find(|(n, _)| *n == name) {
+                catalogs.push(toml::from_str(content).expect("built-in catalog is valid TOML"));
+            }
+        }
+
+        Self { catalogs }
+    }
+
+    pub fn text<'a>(&'a self, key: &'a str) -> &'a str {
+        self.catalogs
+            .iter()
+            .find_map(|c| c.ui.get(key))
+            .map_or(key, String::as_str)
+    }
+
+    pub fn description<'a>(&'a self, info: &IconInfo) -> &'a str {
+        let key = if info.is_unknown() {
+            "unknown".to_string()
+        } else {
+            info.code.to_string()
+        };
+        self.catalogs
+            .iter()
+            .find_map(|c| c.weather.get(&key))
+            .map_or(info.description, String::as_str)
+    }
+
+    pub fn weekday(&self, day: Weekday, long: bool) -> String {
+        let idx = day.num_days_from_monday() as usize;
+        self.catalogs
+            .iter()
+            .find_map(|c| {
+                let names = if long { &c.days.long } else { &c.days.short };
+                names.get(idx)
+            })
+            .cloned()
+            .unwrap_or_else(|| format!("{day:?}"))
+    }
+
+    /// `month` counts from 1, as `Datelike::month` does.
+    pub fn month(&self, month: u32, long: bool) -> String {
+        let idx = month as usize - 1;
+        self.catalogs
+            .iter()
+            .find_map(|c| {
+                let names = if long {
+                    &c.months.long
+                } else {
+                    &c.months.short
+                };
+                names.get(idx)
+            })
+            .cloned()
+            .unwrap_or_else(|| month.to_string())
+    }
+
+    pub fn beaufort(&self, force: u8) -> &str {
+        self.catalogs
+            .iter()
+            .find_map(|c| c.beaufort.names.get(force as usize))
+            .map_or("", String::as_str)
+    }
+
+    /// `strftime` formatting with `%a`/`%A` and `%b`/`%B` taken from the
+    /// catalog instead of chrono's English names. `fmt` must have passed
+    /// `parse_date_format`.
+    pub fn format_date(&self, date: NaiveDate, fmt: &str) -> String {
+        let mut localized = String::with_capacity(fmt.len());
+        let mut chars = fmt.chars();
+        while let Some(ch) = chars.next() {
+            if ch != '%' {
+                localized.push(ch);
+                continue;
+            }
+            match chars.next() {
+                Some('a') => {
+                    localized.push_str(&escape_percent(&self.weekday(date.weekday(), false)))
+                }
+                Some('A') => {
+                    localized.push_str(&escape_percent(&self.weekday(date.weekday(), true)))
+                }
+                // `%h` is chrono's alias for `%b`
+                Some('b' | 'h') => {
+                    localized.push_str(&escape_percent(&self.month(date.month(), false)))
+                }
+                Some('B') => localized.push_str(&escape_percent(&self.month(date.month(), true))),
+                Some(other) => {
+                    localized.push('%');
+                    localized.push(other);
+                }
+                None => localized.push('%'),
+            }
+        }
+        date.format(&localized).to_string()
+    }
+}
+
+fn escape_percent(s: &str) -> String {
+    s.replace('%', "%%")
+}
+
+/// Clap value parser for `--date-format`. chrono panics at format time on
+/// invalid specifiers and on those a date cannot fill (`%H`, `%z`, ...), so
+/// a sample date is formatted up front.
+pub fn parse_date_format(s: &str) -> Result<String, String> {
+    let sample = NaiveDate::from_ymd_opt(2024, 5, 14).expect("valid sample date");
+    let mut out = String::new();
+    write!(out, "{}", sample.format(s))
+        .map_err(|_| format!("invalid date format '{s}' (only date specifiers are allowed)"))?;
+    Ok(s.to_string())
+}
+
+fn lang_from_env() -> String {
+    ["LC_ALL", "LC_TIME", "LANG"]
+        .iter()
+        .filter_map(|var| env::var(var).ok())
+        .find(|v| !v.is_empty())
+        .unwrap_or_default()
+}
+
+// "de_AT.UTF-8" -> ["de_AT", "de", "en"]
+fn candidates(lang: &str) -> Vec<String> {
+    let base = lang
+        .split(['.', '@'])
+        .next()
+        .unwrap_or("")
+        .replace('-', "_");
+    let mut names = Vec::new();
+    if !base.is_empty() && base != "C" && base != "POSIX" {
+        names.push(base.clone());
+        if let Some((primary, _)) = base.split_once('_') {
+            names.push(primary.to_string());
+        }
+    }
+    if !names.iter().any(|n| n == "en") {
+        names.push("en".to_string());
+    }
+    names.dedup();
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn date_format_accepts_date_specifiers() {
+        assert!(parse_date_format("%a %d").is_ok());
+        assert!(parse_date_format("%A, %d. %B %Y").is_ok());
+        assert!(parse_date_format("100%% %j").is_ok());
+    }
+
+    #[test]
+    fn date_format_rejects_what_a_date_cannot_fill() {
+        for fmt in ["%H", "%H:%M", "%S", "%p", "%Z", "%z", "%:z", "%Q", "%"] {
+            assert!(parse_date_format(fmt).is_err(), "{fmt} was accepted");
+        }
+    }
+
+    #[test]
+    fn format_date_uses_catalog_day_names() {
+        let locale = Locale::load(Some("de"));
+        let date = NaiveDate::from_ymd_opt(2024, 5, 13).unwrap();
+        assert_eq!(locale.format_date(date, "%A, %d."), "Montag, 13.");
+        assert_eq!(locale.format_date(date, "%a 100%%"), "Mo 100%");
+    }
+
+    #[test]
+    fn description_of_unknown_codes() {
+        use crate::icons::{get_icon, IconSet};
+        let locale = Locale::load(Some("de"));
+        assert_eq!(
+            locale.description(&get_icon(42, true, &IconSet::Nerd)),
+            "Unbekannt"
+        );
+        assert_ne!(
+            locale.description(&get_icon(3, true, &IconSet::Nerd)),
+            "Unbekannt"
+        );
+    }
+
+    #[test]
+    fn format_date_uses_catalog_month_names() {
+        let date = NaiveDate::from_ymd_opt(2024, 3, 5).unwrap();
+        let de = Locale::load(Some("de"));
+        assert_eq!(de.format_date(date, "%d. %B"), "05. März");
+        assert_eq!(de.format_date(date, "%b/%h"), "Mär/Mär");
+        let es = Locale::load(Some("es"));
+        assert_eq!(es.format_date(date, "%A %e de %B"), "martes  5 de marzo");
+    }
+}
@@ -0,0 +1,114 @@
+use std::collections::BTreeMap;
+use std::io::{self, BufRead};
+use std::thread;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::BarConfig;
+use crate::log;
+use crate::output::{class_color, BarOutput, Renderer};
+use crate::waybar::pango_escape;
+
+/// Block name, which click events refer back to.
+const NAME: &str = "meteobar";
+
+/// Blocks of the i3bar protocol. Both i3bar and swaybar accept Pango markup
+/// in them, so icons and `{temp_colored}` look as they do in Waybar.
+pub struct I3bar {
+    class_colors: BTreeMap<String, String>,
+    /// Continuous protocol: a header, then one array of blocks per line
+    stream: bool,
+}
+
+#[derive(Serialize)]
+struct Block<'a> {
+    name: &'static str,
+    full_text: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    short_text: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    color: Option<&'a str>,
+    urgent: bool,
+    markup: &'static str,
+}
+
+impl I3bar {
+    pub fn new(config: &BarConfig, stream: bool) -> Result<Self, String> {
+        Ok(Self {
+            class_colors: config.class_colors("i3bar")?,
+            stream,
+        })
+    }
+}
+
+impl Renderer for I3bar {
+    fn escape(&self, text: &str) -> String {
+        pango_escape(text)
+    }
+
+    fn colored(&self, color: &str, text: &str) -> String {
+        format!("<span foreground='{color}'>{text}</span>")
+    }
+
+    fn icon(&self, icon: &str, _glyph: &str) -> String {
+        icon.to_string()
+    }
+
+    fn header(&self) -> Option<String> {
+        // The endless array starts with an empty status line, so every
+        // update can be prefixed with a comma, like i3status does
+        self.stream
+            .then(|| r#"{"version":1,"click_events":true}"#.to_string() + "\n[\n[]")
+    }
+
+    fn emit(&self, output: &BarOutput) -> String {
+        let block = Block {
+            name: NAME,
+            full_text: &output.text,
+            short_text: output.short_text.as_deref(),
+            color: class_color(&self.class_colors, &output.class).map(String::as_str),
+            urgent: output.urgent,
+            markup: "pango",
+        };
+        let json = serde_json::to_string(&block)
+            .unwrap_or_else(|_| r#"{"name":"meteobar","full_text":"?"}"#.to_string());
+        if self.stream {
+            format!(",[{json}]")
+        } else {
+            json
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct ClickEvent {
+    #[serde(default)]
+    name: String,
+    button: u32,
+}
+
+/// Reads the bar's click events from stdin on a thread of its own and calls
+/// `on_click` with the mouse button of each click on meteobar's block, until
+/// it returns false or stdin is closed.
+pub fn read_clicks(on_click: impl Fn(u32) -> bool + Send + 'static) {
+    thread::spawn(move || {
+        for line in io::stdin().lock().lines() {
+            let Ok(line) = line else { return };
+            // Another endless array: `[`, then one event per line, all but
+            // the first prefixed with a comma
+            let event = line.trim().trim_start_matches(['[', ',']).trim_start();
+            if event.is_empty() {
+                continue;
+            }
+            match serde_json::from_str::<ClickEvent>(event) {
+                Ok(click) if click.name == NAME => {
+                    if !on_click(click.button) {
+                        return;
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => log::debug(format_args!("ignoring click event {event}: {e}")),
+            }
+        }
+    });
+}
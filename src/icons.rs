@@ -1,10 +1,34 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::Path;
+use std::ptr;
+use std::sync::Arc;
+
+use serde::Deserialize;
+
+use crate::log;
+
 pub struct IconInfo {
+    pub code: u8,
+    /// Glyph with the Pango markup its set needs
     pub icon: String,
+    /// The bare glyph, for bars that do not speak Pango
+    pub glyph: String,
+    /// Cells the icon takes, if its set says so rather than leaving it to
+    /// the width of its characters
+    pub width: Option<usize>,
     pub css_class: &'static str,
     pub description: &'static str,
 }
 
-#[derive(Clone, clap::ValueEnum)]
+impl IconInfo {
+    /// Whether `code` is not a weather code meteobar knows.
+    pub fn is_unknown(&self) -> bool {
+        ptr::eq(find_entry(self.code), &UNKNOWN)
+    }
+}
+
+#[derive(Clone)]
 pub enum IconSet {
     /// Material Design weather icons (outline style)
     Nerd,
@@ -14,6 +38,125 @@ pub enum IconSet {
     Emoji,
     /// Font Awesome Free Solid weather icons
     Fontawesome,
+    /// User-defined glyphs loaded from a TOML file
+    Custom(Arc<CustomIconSet>),
+}
+
+impl IconSet {
+    /// Number of monospace cells `ch` occupies in Pango if it is one of this
+    /// set's glyphs. Others are left to `unicode-width`, which measures emoji
+    /// as wide and private-use glyphs as narrow.
+    pub fn glyph_width(&self, ch: char) -> Option<usize> {
+        match (self, ch as u32) {
+            (IconSet::Weather, 0xE300..=0xE3EB) => Some(2),
+            (IconSet::Fontawesome, 0xF000..=0xF8FF) => Some(2),
+            (IconSet::Custom(custom), _) => custom.fallback.glyph_width(ch),
+            _ => None,
+        }
+    }
+}
+
+pub fn parse_icon_set(s: &str) -> Result<IconSet, String> {
+    if let Some(path) = s.strip_prefix("custom:") {
+        return CustomIconSet::load(Path::new(path)).map(|c| IconSet::Custom(Arc::new(c)));
+    }
+    parse_builtin(s).ok_or_else(|| {
+        format!(
+            "unknown icon set '{s}' (expected nerd, weather, emoji, fontawesome or custom:<file>)"
+        )
+    })
+}
+
+fn parse_builtin(s: &str) -> Option<IconSet> {
+    match s {
+        "nerd" => Some(IconSet::Nerd),
+        "weather" => Some(IconSet::Weather),
+        "emoji" => Some(IconSet::Emoji),
+        "fontawesome" => Some(IconSet::Fontawesome),
+        _ => None,
+    }
+}
+
+pub struct CustomIconSet {
+    fallback: IconSet,
+    /// Cells each of its own icons occupies, when the file declares it
+    width: Option<usize>,
+    wrap: Option<String>,
+    rain: Option<String>,
+    unknown: Option<String>,
+    codes: HashMap<u8, (String, String)>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CustomIconFile {
+    #[serde(default = "default_fallback")]
+    fallback: String,
+    width: Option<usize>,
+    wrap: Option<String>,
+    rain: Option<String>,
+    unknown: Option<String>,
+    #[serde(default)]
+    codes: HashMap<String, CustomGlyph>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum CustomGlyph {
+    Both(String),
+    DayNight { day: String, night: Option<String> },
+}
+
+fn default_fallback() -> String {
+    "nerd".into()
+}
+
+impl CustomIconSet {
+    fn load(path: &Path) -> Result<Self, String> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("failed to read icon set {}: {e}", path.display()))?;
+        let file: CustomIconFile = toml::from_str(&content)
+            .map_err(|e| format!("invalid icon set {}: {e}", path.display()))?;
+
+        let fallback = parse_builtin(&file.fallback).ok_or_else(|| {
+            format!(
+                "icon set {}: fallback must be nerd, weather, emoji or fontawesome, not '{}'",
+                path.display(),
+                file.fallback
+            )
+        })?;
+
+        let mut codes = HashMap::new();
+        for (key, glyph) in file.codes {
+            let code: u8 = key
+                .parse()
+                .map_err(|_| format!("icon set {}: invalid WMO code '{key}'", path.display()))?;
+            let pair = match glyph {
+                CustomGlyph::Both(g) => (g.clone(), g),
+                CustomGlyph::DayNight { day, night } => {
+                    let night = night.unwrap_or_else(|| day.clone());
+                    (day, night)
+                }
+            };
+            codes.insert(code, pair);
+        }
+
+        Ok(Self {
+            fallback,
+            width: file.width,
+            wrap: file.wrap,
+            rain: file.rain,
+            unknown: file.unknown,
+            codes,
+        })
+    }
+
+    fn wrap(&self, raw: &str) -> String {
+        match &self.wrap {
+            Some(wrap) => wrap.replace("{icon}", raw),
+            None => raw.to_string(),
+        }
+    }
 }
 
 struct IconEntry {
@@ -87,16 +230,54 @@ const ICONS: &[(u8, IconEntry)] = &[
     (99, IconEntry { day_nerd: "󰖓", night_nerd: "󰖓", day_weather: "\u{e31d}", night_weather: "\u{e31d}", day_emoji: "⛈️",  night_emoji: "⛈️",  day_fa: "\u{f76c}", night_fa: "\u{f76c}", css_class: "stormy", description: "Thunderstorm with heavy hail" }),
 ];
 
+// Shown for codes missing from the table rather than pretending the sky is clear
+//   nerd: help_circle_outline \u{f0625}   weather: na \u{e374}   fa: circle-question \u{f059}
+static UNKNOWN: IconEntry = IconEntry { day_nerd: "\u{f0625}", night_nerd: "\u{f0625}", day_weather: "\u{e374}", night_weather: "\u{e374}", day_emoji: "❓", night_emoji: "❓", day_fa: "\u{f059}", night_fa: "\u{f059}", css_class: "unknown", description: "Unknown" };
+
 fn find_entry(code: u8) -> &'static IconEntry {
     ICONS
         .iter()
         .find(|(c, _)| *c == code)
-        .map(|(_, e)| e)
-        .unwrap_or(&ICONS[0].1)
+        .map_or(&UNKNOWN, |(_, entry)| entry)
+}
+
+/// Logs each of `codes` that is shown with the unknown icon, once.
+pub fn log_unmapped(codes: impl IntoIterator<Item = u8>) {
+    let unmapped: BTreeSet<u8> = codes
+        .into_iter()
+        .filter(|code| ptr::eq(find_entry(*code), &UNKNOWN))
+        .collect();
+    for code in unmapped {
+        log::debug(format_args!("unmapped WMO weather code {code}"));
+    }
 }
 
 pub fn get_icon(code: u8, is_day: bool, icon_set: &IconSet) -> IconInfo {
     let entry = find_entry(code);
+    let (glyph, icon, width) = glyph(entry, code, is_day, icon_set);
+    IconInfo {
+        code,
+        icon,
+        glyph,
+        width,
+        css_class: entry.css_class,
+        description: entry.description,
+    }
+}
+
+/// Conditions worth drawing attention to: heavy freezing rain, heavy snow,
+/// violent showers and every thunderstorm.
+pub fn is_severe(code: u8) -> bool {
+    matches!(code, 67 | 75 | 82 | 86 | 95 | 96 | 99)
+}
+
+/// The bare glyph, its Pango form and its declared width.
+fn glyph(
+    entry: &IconEntry,
+    code: u8,
+    is_day: bool,
+    icon_set: &IconSet,
+) -> (String, String, Option<usize>) {
     let raw = match (icon_set, is_day) {
         (IconSet::Nerd, true) => entry.day_nerd,
         (IconSet::Nerd, false) => entry.night_nerd,
@@ -106,16 +287,41 @@ pub fn get_icon(code: u8, is_day: bool, icon_set: &IconSet) -> IconInfo {
         (IconSet::Emoji, false) => entry.night_emoji,
         (IconSet::Fontawesome, true) => entry.day_fa,
         (IconSet::Fontawesome, false) => entry.night_fa,
+        (IconSet::Custom(custom), _) => {
+            // Codes missing from the file fall back to the built-in set
+            let raw = match (custom.codes.get(&code), &custom.unknown) {
+                (Some((day, _)), _) if is_day => day,
+                (Some((_, night)), _) => night,
+                (None, Some(unknown)) if ptr::eq(entry, &UNKNOWN) => unknown,
+                (None, _) => return glyph(entry, code, is_day, &custom.fallback),
+            };
+            return (raw.clone(), custom.wrap(raw), custom.width);
+        }
     };
-    // FA glyphs need Pango markup so Waybar uses the correct font (not the default monospace)
-    let icon = if matches!(icon_set, IconSet::Fontawesome) {
-        format!("<span font='Font Awesome 7 Free Solid'>{raw}</span>")
-    } else {
-        raw.to_string()
-    };
-    IconInfo {
-        icon,
-        css_class: entry.css_class,
-        description: entry.description,
+    (raw.to_string(), markup(raw, icon_set), None)
+}
+
+/// Wraps a raw glyph in whatever Pango markup its icon set needs.
+pub fn markup(raw: &str, icon_set: &IconSet) -> String {
+    match icon_set {
+        // FA glyphs need Pango markup so Waybar uses the correct font (not the default monospace)
+        IconSet::Fontawesome => format!("<span font='Font Awesome 7 Free Solid'>{raw}</span>"),
+        IconSet::Custom(custom) => custom.wrap(raw),
+        _ => raw.to_string(),
     }
 }
+
+/// The precipitation icon in Pango markup, and its declared width.
+pub fn rain_icon(icon_set: &IconSet) -> (String, Option<usize>) {
+    let raw = match icon_set {
+        IconSet::Nerd => "󰖗",
+        IconSet::Weather => "\u{e318}",
+        IconSet::Emoji => "💧",
+        IconSet::Fontawesome => "\u{f73d}",
+        IconSet::Custom(custom) => match &custom.rain {
+            Some(rain) => return (custom.wrap(rain), custom.width),
+            None => return rain_icon(&custom.fallback),
+        },
+    };
+    (markup(raw, icon_set), None)
+}
@@ -0,0 +1,260 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use crate::api::WeatherData;
+use crate::format::degrees_to_cardinal;
+use crate::i18n::Locale;
+use crate::icons::{self, IconSet};
+use crate::output::{BarOutput, Renderer};
+use crate::units::Units;
+
+/// Bumped whenever a field changes meaning or goes away. New fields may
+/// appear without a bump.
+pub const VERSION: u32 = 1;
+
+/// The weather as data, for widget frameworks such as eww or AGS.
+pub struct Json;
+
+impl Renderer for Json {
+    fn escape(&self, text: &str) -> String {
+        text.to_string()
+    }
+
+    fn colored(&self, _color: &str, text: &str) -> String {
+        text.to_string()
+    }
+
+    fn icon(&self, _icon: &str, glyph: &str) -> String {
+        glyph.to_string()
+    }
+
+    fn wants_document(&self) -> bool {
+        true
+    }
+
+    fn emit(&self, output: &BarOutput) -> String {
+        let json = match &output.document {
+            Some(document) => serde_json::to_string(document),
+            None => serde_json::to_string(&ErrorDocument {
+                version: VERSION,
+                error: output.error.as_deref().unwrap_or_default(),
+                class: &output.class,
+            }),
+        };
+        json.unwrap_or_else(|_| {
+            format!(r#"{{"version":{VERSION},"error":"serialization error","class":["error"]}}"#)
+        })
+    }
+}
+
+#[derive(Serialize)]
+struct ErrorDocument<'a> {
+    version: u32,
+    error: &'a str,
+    class: &'a [String],
+}
+
+#[derive(Serialize)]
+pub struct Document {
+    version: u32,
+    /// The bar text, without markup
+    text: String,
+    class: Vec<String>,
+    stale: bool,
+    /// Unix time of the fetch
+    updated: i64,
+    location: Location,
+    units: UnitLabels,
+    current: Current,
+    hourly: Vec<Hour>,
+    daily: Vec<Day>,
+}
+
+#[derive(Serialize)]
+struct Location {
+    city: String,
+    lat: f64,
+    lon: f64,
+    timezone: String,
+    utc_offset_seconds: i32,
+}
+
+#[derive(Serialize)]
+struct UnitLabels {
+    temp: &'static str,
+    wind: &'static str,
+    pressure: &'static str,
+    precip: &'static str,
+}
+
+#[derive(Serialize)]
+struct Condition {
+    code: u8,
+    class: &'static str,
+    description: String,
+    /// Glyph of the `--icons` set
+    icon: String,
+    /// Glyph of every built-in set
+    icons: BTreeMap<&'static str, String>,
+}
+
+#[derive(Serialize)]
+struct Current {
+    temp: f64,
+    feels_like: Option<f64>,
+    humidity: Option<f64>,
+    wind: Option<f64>,
+    /// Degrees the wind comes from
+    wind_direction: Option<f64>,
+    wind_cardinal: Option<&'static str>,
+    gusts: Option<f64>,
+    pressure: Option<f64>,
+    precipitation: Option<f64>,
+    is_day: bool,
+    #[serde(flatten)]
+    condition: Condition,
+}
+
+#[derive(Serialize)]
+struct Hour {
+    time: String,
+    temp: f64,
+    rain_chance: Option<u8>,
+    is_day: bool,
+    #[serde(flatten)]
+    condition: Condition,
+}
+
+/// Always with the day icon
+#[derive(Serialize)]
+struct Day {
+    date: String,
+    min: f64,
+    max: f64,
+    rain_chance: Option<u8>,
+    wind_max: Option<f64>,
+    sunrise: Option<String>,
+    sunset: Option<String>,
+    #[serde(flatten)]
+    condition: Condition,
+}
+
+/// Where and when the weather is from.
+pub struct Source<'a> {
+    pub city: &'a str,
+    pub lat: f64,
+    pub lon: f64,
+    pub updated: i64,
+    pub stale: bool,
+}
+
+pub struct DocumentOptions<'a> {
+    pub icon_set: &'a IconSet,
+    pub locale: &'a Locale,
+    pub units: &'a Units,
+    pub days: u8,
+    pub hours: u8,
+}
+
+const BUILTIN_SETS: [(&str, IconSet); 4] = [
+    ("nerd", IconSet::Nerd),
+    ("weather", IconSet::Weather),
+    ("emoji", IconSet::Emoji),
+    ("fontawesome", IconSet::Fontawesome),
+];
+
+impl Document {
+    /// `weather` must already be converted to `opts.units`.
+    pub fn new(
+        weather: &WeatherData,
+        source: &Source,
+        text: String,
+        class: Vec<String>,
+        opts: &DocumentOptions,
+    ) -> Self {
+        let current = &weather.current;
+        // Entries missing a required value (a truncated cache) are left out
+        let hourly = weather.hourly.as_ref().map_or_else(Vec::new, |hourly| {
+            (0..hourly.time.len().min(opts.hours as usize))
+                .filter_map(|i| {
+                    // Caches from before hourly `is_day` was fetched
+                    let is_day = hourly.is_day.get(i).is_none_or(|d| *d == 1);
+                    Some(Hour {
+                        time: hourly.time[i].clone(),
+                        temp: *hourly.temperature_2m.get(i)?,
+                        rain_chance: hourly.precipitation_probability.get(i).copied(),
+                        is_day,
+                        condition: condition(*hourly.weather_code.get(i)?, is_day, opts),
+                    })
+                })
+                .collect()
+        });
+        let daily = &weather.daily;
+        let days = (0..daily.time.len().min(opts.days as usize))
+            .filter_map(|i| {
+                Some(Day {
+                    date: daily.time[i].clone(),
+                    min: *daily.temperature_2m_min.get(i)?,
+                    max: *daily.temperature_2m_max.get(i)?,
+                    rain_chance: daily.precipitation_probability_max.get(i).copied(),
+                    wind_max: daily.wind_speed_10m_max.get(i).copied(),
+                    sunrise: daily.sunrise.get(i).cloned(),
+                    sunset: daily.sunset.get(i).cloned(),
+                    condition: condition(*daily.weather_code.get(i)?, true, opts),
+                })
+            })
+            .collect();
+
+        Self {
+            version: VERSION,
+            text,
+            class,
+            stale: source.stale,
+            updated: source.updated,
+            location: Location {
+                city: source.city.to_string(),
+                lat: source.lat,
+                lon: source.lon,
+                timezone: weather.timezone.clone(),
+                utc_offset_seconds: weather.utc_offset_seconds,
+            },
+            units: UnitLabels {
+                temp: opts.units.temp.label(),
+                wind: opts.units.wind.label(),
+                pressure: opts.units.pressure.label(),
+                precip: opts.units.precip.label(),
+            },
+            current: Current {
+                temp: current.temperature_2m,
+                feels_like: current.apparent_temperature,
+                humidity: current.relative_humidity_2m,
+                wind: current.wind_speed_10m,
+                wind_direction: current.wind_direction_10m,
+                wind_cardinal: current.wind_direction_10m.map(degrees_to_cardinal),
+                gusts: current.wind_gusts_10m,
+                pressure: current.pressure_msl,
+                precipitation: current.precipitation,
+                is_day: current.is_day == 1,
+                condition: condition(current.weather_code, current.is_day == 1, opts),
+            },
+            hourly,
+            daily: days,
+        }
+    }
+}
+
+fn condition(code: u8, is_day: bool, opts: &DocumentOptions) -> Condition {
+    let info = icons::get_icon(code, is_day, opts.icon_set);
+    let icons = BUILTIN_SETS
+        .iter()
+        .map(|(name, set)| (*name, icons::get_icon(code, is_day, set).glyph))
+        .collect();
+    Condition {
+        code,
+        class: info.css_class,
+        description: opts.locale.description(&info).to_string(),
+        icon: info.glyph,
+        icons,
+    }
+}
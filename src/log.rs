@@ -0,0 +1,16 @@
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static DEBUG: AtomicBool = AtomicBool::new(false);
+
+pub fn enable_debug() {
+    DEBUG.store(true, Ordering::Relaxed);
+}
+
+/// Writes a diagnostic line to stderr (which Waybar keeps in its log) when
+/// `--debug` is set.
+pub fn debug(args: fmt::Arguments) {
+    if DEBUG.load(Ordering::Relaxed) {
+        eprintln!("meteobar: {args}");
+    }
+}
@@ -1,20 +1,42 @@
 mod api;
 mod cache;
+mod config;
 mod format;
+mod i18n;
+mod i3bar;
 mod icons;
+mod json;
+mod log;
+mod output;
+mod polybar;
+mod template;
+mod terminal;
 mod theme;
+mod tmux;
+mod units;
+mod watch;
 mod waybar;
+mod xmobar;
 
+use std::iter;
 use std::path::PathBuf;
-use std::time::Duration;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
 
 use clap::Parser;
 
-use api::ResolvedLocation;
+use api::{FetchError, ResolvedLocation};
 use cache::CacheEntry;
-use format::FormatData;
+use format::{FormatData, FormatOptions, TimeFormat, WindArrow};
 use icons::IconSet;
-use waybar::{TooltipFormat, WaybarOutput};
+use output::{BarOutput, OutputFormat, Renderer};
+use template::TooltipTemplate;
+use theme::Palette;
+use units::{PrecipUnit, PressureUnit, TempUnit, UnitPreset, Units, WindUnit};
+use waybar::{
+    BorderStyle, DateOptions, ErrorKind, FrameOptions, Overflow, TooltipOptions, TooltipSections,
+};
 
 #[derive(Parser)]
 #[command(name = "meteobar", version, about = "Weather widget for Waybar using Open-Meteo")]
@@ -34,8 +56,55 @@ struct Cli {
     #[arg(long, default_value = "{icon} {temp}°")]
     format: String,
 
-    #[arg(long, value_enum, default_value_t = TooltipFormat::Days)]
-    tooltip_format: TooltipFormat,
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = OutputFormat::Waybar,
+        help = "Bar to print for; only waybar and terminal show the tooltip"
+    )]
+    output: OutputFormat,
+
+    #[arg(
+        long,
+        value_name = "TEMPLATE",
+        help = "Shorter bar text for when space runs out (i3bar)"
+    )]
+    short_format: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "TEMPLATE",
+        help = "Another bar text; clicks cycle through it and --format (i3bar with --daemon)"
+    )]
+    alt_format: Vec<String>,
+
+    #[arg(
+        long,
+        alias = "tooltip-format",
+        value_name = "LIST",
+        default_value = "days",
+        help = "Comma-separated tooltip sections: current, stats, sun, hourly, daily (or days, hours, both)"
+    )]
+    tooltip_sections: TooltipSections,
+
+    #[arg(long, value_enum, default_value_t = BorderStyle::Rounded)]
+    tooltip_border: BorderStyle,
+
+    #[arg(long, value_name = "N", default_value_t = waybar::DEFAULT_MIN_WIDTH)]
+    tooltip_min_width: usize,
+
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u16).range(10..))]
+    tooltip_max_width: Option<u16>,
+
+    #[arg(long, value_enum, default_value_t = Overflow::Truncate)]
+    tooltip_overflow: Overflow,
+
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "Render the tooltip from a template file"
+    )]
+    tooltip_template: Option<PathBuf>,
 
     #[arg(long, default_value_t = 3, value_parser = clap::value_parser!(u8).range(1..=7))]
     days: u8,
@@ -43,31 +112,153 @@ struct Cli {
     #[arg(long, default_value_t = 0, value_parser = clap::value_parser!(u8).range(0..=24))]
     hours: u8,
 
-    #[arg(long, value_enum, default_value_t = CliUnits::Metric)]
-    units: CliUnits,
+    #[arg(long, value_enum, default_value_t = UnitPreset::Metric)]
+    units: UnitPreset,
 
-    #[arg(long, value_enum, default_value_t = IconSet::Nerd)]
+    #[arg(long, value_enum, help = "Temperature unit [default: from --units]")]
+    temp_unit: Option<TempUnit>,
+
+    #[arg(long, value_enum, help = "Wind speed unit [default: from --units]")]
+    wind_unit: Option<WindUnit>,
+
+    #[arg(long, value_enum, help = "Pressure unit [default: from --units]")]
+    pressure_unit: Option<PressureUnit>,
+
+    #[arg(long, value_enum, help = "Precipitation unit [default: from --units]")]
+    precip_unit: Option<PrecipUnit>,
+
+    #[arg(
+        long,
+        default_value = "nerd",
+        value_parser = icons::parse_icon_set,
+        help = "Icon set: nerd, weather, emoji, fontawesome or custom:<file>"
+    )]
     icons: IconSet,
 
+    #[arg(long, help = "Use the --icons set in the tooltip as well")]
+    tooltip_bar_icons: bool,
+
     #[arg(long)]
     cache_dir: Option<PathBuf>,
 
     #[arg(long)]
     no_cache: bool,
 
+    #[arg(
+        long,
+        value_name = "SECS",
+        help = "Use cached weather up to SECS old without fetching"
+    )]
+    max_age: Option<u64>,
+
     #[arg(long, default_value_t = 10, value_parser = clap::value_parser!(u64).range(1..=60))]
     timeout: u64,
-}
 
-#[derive(Clone, clap::ValueEnum)]
-enum CliUnits {
-    Metric,
-    Imperial,
+    #[arg(
+        long,
+        default_value = "%a %d",
+        value_parser = i18n::parse_date_format,
+        help = "strftime format for forecast days"
+    )]
+    date_format: String,
+
+    #[arg(
+        long,
+        value_name = "FORMAT",
+        default_value = "24h",
+        help = "Clock format for hourly rows and sunrise/sunset: 24h, 12h or a strftime string"
+    )]
+    time_format: TimeFormat,
+
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = WindArrow::To,
+        help = "Point {wind_arrow} where the wind blows to or comes from"
+    )]
+    wind_arrow: WindArrow,
+
+    #[arg(
+        long,
+        help = "Label every forecast day with --date-format, without Today and Tomorrow"
+    )]
+    no_relative_days: bool,
+
+    #[arg(long, help = "Highlight Saturday and Sunday in the forecast")]
+    highlight_weekends: bool,
+
+    #[arg(
+        long,
+        help = "Language for descriptions and labels [default: from LC_TIME/LANG]"
+    )]
+    lang: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "Config file [default: ~/.config/meteobar/config.toml]"
+    )]
+    config: Option<PathBuf>,
+
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "Tooltip colors from a pywal .json, base16/base24 .yaml or Omarchy-style .toml file"
+    )]
+    theme: Option<PathBuf>,
+
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = Palette::Auto,
+        help = "Built-in colors when no theme file is found"
+    )]
+    palette: Palette,
+
+    #[arg(long, help = "Keep running and print a new line on every update")]
+    daemon: bool,
+
+    #[arg(
+        long,
+        value_name = "SECS",
+        default_value_t = 600,
+        value_parser = clap::value_parser!(u64).range(60..),
+        help = "Seconds between weather updates with --daemon"
+    )]
+    interval: u64,
+
+    #[arg(long, help = "Print diagnostics to stderr")]
+    debug: bool,
 }
 
 fn main() {
     let cli = Cli::parse();
-    let colors = theme::ThemeColors::load();
+    if cli.debug {
+        log::enable_debug();
+    }
+    let locale = i18n::Locale::load(cli.lang.as_deref());
+    let frame = FrameOptions {
+        border: cli.tooltip_border,
+        min_width: cli.tooltip_min_width,
+        max_width: cli.tooltip_max_width.map(usize::from),
+        overflow: cli.tooltip_overflow,
+    };
+
+    let preset = Units::preset(cli.units);
+    let units = Units {
+        temp: cli.temp_unit.unwrap_or(preset.temp),
+        wind: cli.wind_unit.unwrap_or(preset.wind),
+        pressure: cli.pressure_unit.unwrap_or(preset.pressure),
+        precip: cli.precip_unit.unwrap_or(preset.precip),
+    };
+
+    let setup = load_config(&cli, &units);
+    // The daemon shows the error until the file is fixed
+    if let (Some(msg), false) = (&setup.error, cli.daemon) {
+        let output = waybar::error_output(ErrorKind::Config, msg, &frame, &locale, &setup.colors);
+        print_output(setup.renderer.as_ref(), &output);
+        return;
+    }
 
     let cache_dir = cli
         .cache_dir
@@ -84,49 +275,259 @@ fn main() {
         .build()
         .expect("failed to build HTTP client");
 
-    let units = match cli.units {
-        CliUnits::Metric => api::Units::Metric,
-        CliUnits::Imperial => api::Units::Imperial,
+    let dates = DateOptions {
+        format: cli.date_format.clone(),
+        relative: !cli.no_relative_days,
+        highlight_weekends: cli.highlight_weekends,
     };
-    let unit_label = match cli.units {
-        CliUnits::Metric => "°C",
-        CliUnits::Imperial => "°F",
+
+    let ctx = RenderContext {
+        units,
+        template: setup.template,
+        dates,
+        frame,
+        locale,
+        colors: setup.colors,
+        renderer: setup.renderer,
+        view: 0,
+        config_error: setup.error,
     };
 
-    let result = run_pipeline(&cli, &client, &units, &cache_dir);
+    if cli.daemon {
+        run_daemon(&cli, &client, &cache_dir, ctx);
+    } else {
+        let output = refresh(&cli, &client, &cache_dir, &ctx).0;
+        print_output(ctx.renderer.as_ref(), &output);
+    }
+}
+
+/// What is read from files other than the cache, and reloaded by `--daemon`
+/// when they change.
+struct Setup {
+    /// Theme colors with the config file's overrides and gradient applied
+    colors: theme::ThemeColors,
+    renderer: Box<dyn Renderer>,
+    template: Option<TooltipTemplate>,
+    /// Why the config file or template couldn't be used; the renderer and
+    /// template are then the defaults
+    error: Option<String>,
+}
 
-    match result {
+fn load_config(cli: &Cli, units: &Units) -> Setup {
+    // Loaded once either way, as finding the theme may ask the desktop portal
+    let mut colors = theme::ThemeColors::load(cli.theme.as_deref(), cli.palette);
+    match apply_config(cli, units, &mut colors) {
+        Ok((renderer, template)) => Setup {
+            colors,
+            renderer,
+            template,
+            error: None,
+        },
+        Err(message) => Setup {
+            colors,
+            renderer: output::renderer(cli.output, &config::Config::default(), cli.daemon)
+                .expect("the default config is valid"),
+            template: None,
+            error: Some(message),
+        },
+    }
+}
+
+/// Applies the config file to `colors` and builds what else it configures.
+fn apply_config(
+    cli: &Cli,
+    units: &Units,
+    colors: &mut theme::ThemeColors,
+) -> Result<(Box<dyn Renderer>, Option<TooltipTemplate>), String> {
+    let config = config::Config::load(cli.config.as_deref())?;
+    colors.apply_overrides(&config.colors)?;
+    colors.set_gradient(&config.temp_gradient, units.temp)?;
+    let renderer = output::renderer(cli.output, &config, cli.daemon)?;
+    let template = cli
+        .tooltip_template
+        .as_deref()
+        .map(TooltipTemplate::load)
+        .transpose()?;
+    Ok((renderer, template))
+}
+
+/// Weather behind the last successful output, kept for re-rendering.
+struct Snapshot {
+    weather: api::WeatherData,
+    city: String,
+    lat: f64,
+    lon: f64,
+    /// Unix time of the fetch
+    updated: i64,
+    stale: bool,
+}
+
+impl Snapshot {
+    fn from_cache(entry: CacheEntry, stale: bool) -> Self {
+        Self {
+            weather: entry.weather,
+            city: entry.city,
+            lat: entry.lat,
+            lon: entry.lon,
+            updated: entry.timestamp,
+            stale,
+        }
+    }
+}
+
+/// Fetches (or falls back to the cache) and renders once.
+fn refresh(
+    cli: &Cli,
+    client: &reqwest::blocking::Client,
+    cache_dir: &std::path::Path,
+    ctx: &RenderContext,
+) -> (BarOutput, Option<Snapshot>) {
+    let snapshot = match run_pipeline(cli, client, cache_dir) {
         PipelineResult::Fresh {
             weather,
             city,
             lat,
             lon,
         } => {
+            let entry = CacheEntry {
+                version: cache::VERSION,
+                weather,
+                city,
+                location_query: cli.location.clone(),
+                lat,
+                lon,
+                timestamp: chrono::Utc::now().timestamp(),
+            };
             if !cli.no_cache {
-                let entry = CacheEntry {
-                    weather: weather.clone(),
-                    city: city.clone(),
-                    location_query: cli.location.clone(),
-                    lat,
-                    lon,
-                    timestamp: chrono::Utc::now().timestamp(),
-                };
-                let _ = cache::save(&entry, &cache_dir);
+                let _ = cache::save(&entry, cache_dir);
+            }
+            Snapshot::from_cache(entry, false)
+        }
+        PipelineResult::Cached(entry) => Snapshot::from_cache(entry, false),
+        PipelineResult::Stale(entry) => Snapshot::from_cache(entry, true),
+        PipelineResult::Error(kind, msg) => {
+            let output = waybar::error_output(kind, &msg, &ctx.frame, &ctx.locale, &ctx.colors);
+            return (output, None);
+        }
+    };
+    let output = build_output(&snapshot, cli, ctx);
+    (output, Some(snapshot))
+}
+
+/// Something `--daemon` reacts to between updates.
+enum Event {
+    /// A theme file, the config file or the tooltip template changed
+    FilesChanged,
+    /// Mouse button of an i3bar click
+    Click(u32),
+}
+
+/// Prints a line every `--interval` seconds, and again right away with the
+/// last weather whenever the theme or config changes or a click switches the
+/// view.
+fn run_daemon(
+    cli: &Cli,
+    client: &reqwest::blocking::Client,
+    cache_dir: &std::path::Path,
+    mut ctx: RenderContext,
+) {
+    let (tx, events) = mpsc::channel();
+    let files_tx = tx.clone();
+    let mut files = theme::ThemeColors::sources(cli.theme.as_deref());
+    files.extend(config::path(cli.config.as_deref()));
+    files.extend(cli.tooltip_template.clone());
+    watch::spawn(files, move || files_tx.send(Event::FilesChanged).is_ok());
+    if cli.output == OutputFormat::I3bar {
+        i3bar::read_clicks(move |button| tx.send(Event::Click(button)).is_ok());
+    }
+    if let Some(header) = ctx.renderer.header() {
+        println!("{header}");
+    }
+    // Only prints something if the config is broken
+    reprint(None, cli, &ctx);
+
+    let interval = Duration::from_secs(cli.interval);
+    let mut last: Option<Snapshot> = None;
+    let mut next_fetch = Instant::now();
+    let mut reload_at: Option<Instant> = None;
+
+    loop {
+        let now = Instant::now();
+        // Fetched as soon as a broken config is fixed
+        if now >= next_fetch && ctx.config_error.is_none() {
+            let (output, snapshot) = refresh(cli, client, cache_dir, &ctx);
+            print_output(ctx.renderer.as_ref(), &output);
+            match (snapshot, &mut last) {
+                (Some(snapshot), _) => last = Some(snapshot),
+                // Re-rendered with new colors or views until the next fetch,
+                // but no longer current
+                (None, Some(last)) => last.stale = true,
+                (None, None) => {}
             }
-            let output = build_output(&weather, &city, &cli, unit_label, false, &colors);
-            print_and_exit(output);
+            next_fetch = Instant::now() + interval;
         }
-        PipelineResult::Stale { weather, city } => {
-            let output = build_output(&weather, &city, &cli, unit_label, true, &colors);
-            print_and_exit(output);
+        if reload_at.is_some_and(|at| now >= at) {
+            reload_at = None;
+            let setup = load_config(cli, &ctx.units);
+            match setup.error {
+                None => {
+                    ctx.colors = setup.colors;
+                    ctx.renderer = setup.renderer;
+                    ctx.template = setup.template;
+                    ctx.config_error = None;
+                }
+                Some(e) if ctx.config_error.is_some() => {
+                    ctx.colors = setup.colors;
+                    ctx.config_error = Some(e);
+                }
+                Some(e) => log::debug(format_args!("keeping previous colors: {e}")),
+            }
+            reprint(last.as_ref(), cli, &ctx);
         }
-        PipelineResult::Error(msg) => {
-            let output = waybar::error_output(&msg, &colors);
-            print_and_exit(output);
+
+        let fetch_at = ctx.config_error.is_none().then_some(next_fetch);
+        let deadline = [reload_at, fetch_at]
+            .into_iter()
+            .flatten()
+            .min()
+            .unwrap_or(now + interval);
+        let wait = deadline.saturating_duration_since(Instant::now());
+        match events.recv_timeout(wait) {
+            // A theme switch touches several files; let it settle.
+            Ok(Event::FilesChanged) => {
+                reload_at = Some(Instant::now() + Duration::from_millis(200));
+            }
+            Ok(Event::Click(button)) => {
+                let views = cli.alt_format.len() + 1;
+                ctx.view = match button {
+                    // Left click or scrolling down
+                    1 | 5 => (ctx.view + 1) % views,
+                    // Right click or scrolling up
+                    3 | 4 => (ctx.view + views - 1) % views,
+                    _ => continue,
+                };
+                reprint(last.as_ref(), cli, &ctx);
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            // No watcher: just wait for the next fetch
+            Err(RecvTimeoutError::Disconnected) => thread::sleep(wait),
         }
     }
 }
 
+/// Renders the last weather again, e.g. with new colors, or the config
+/// error while there is one.
+fn reprint(last: Option<&Snapshot>, cli: &Cli, ctx: &RenderContext) {
+    let output = match (&ctx.config_error, last) {
+        (Some(msg), _) => {
+            waybar::error_output(ErrorKind::Config, msg, &ctx.frame, &ctx.locale, &ctx.colors)
+        }
+        (None, Some(snapshot)) => build_output(snapshot, cli, ctx),
+        (None, None) => return,
+    };
+    print_output(ctx.renderer.as_ref(), &output);
+}
+
 enum PipelineResult {
     Fresh {
         weather: api::WeatherData,
@@ -134,20 +535,21 @@ enum PipelineResult {
         lat: f64,
         lon: f64,
     },
-    Stale {
-        weather: api::WeatherData,
-        city: String,
-    },
-    Error(String),
+    /// Recent enough for `--max-age`
+    Cached(CacheEntry),
+    Stale(CacheEntry),
+    Error(ErrorKind, String),
 }
 
 fn run_pipeline(
     cli: &Cli,
     client: &reqwest::blocking::Client,
-    units: &api::Units,
     cache_dir: &std::path::Path,
 ) -> PipelineResult {
-    let fresh = try_fresh(cli, client, units, cache_dir);
+    if let Some(entry) = recent_cache(cli, cache_dir) {
+        return PipelineResult::Cached(entry);
+    }
+    let fresh = try_fresh(cli, client, cache_dir);
 
     match fresh {
         Ok(r) => PipelineResult::Fresh {
@@ -156,17 +558,43 @@ fn run_pipeline(
             lat: r.lat,
             lon: r.lon,
         },
-        Err(_) if !cli.no_cache => match cache::load(cache_dir) {
-            Ok(entry) => PipelineResult::Stale {
-                weather: entry.weather,
-                city: entry.city,
-            },
-            Err(cache_err) => PipelineResult::Error(cache_err),
+        Err(FetchError::UnknownLocation(e)) => PipelineResult::Error(ErrorKind::Location, e),
+        Err(FetchError::Failed(e)) if !cli.no_cache => match cache::load(cache_dir) {
+            Ok(Some(entry)) => PipelineResult::Stale(entry),
+            Ok(None) => PipelineResult::Error(ErrorKind::Network, e),
+            Err(cache_err) => PipelineResult::Error(ErrorKind::Cache, cache_err),
         },
-        Err(e) => PipelineResult::Error(e),
+        Err(FetchError::Failed(e)) => PipelineResult::Error(ErrorKind::Network, e),
     }
 }
 
+/// The cached weather if `--max-age` allows it and it is for the same
+/// location and covers as many days and hours.
+fn recent_cache(cli: &Cli, cache_dir: &std::path::Path) -> Option<CacheEntry> {
+    let max_age = cli.max_age.filter(|_| !cli.no_cache)?;
+    let entry = cache::load(cache_dir).ok().flatten()?;
+    let age = chrono::Utc::now().timestamp() - entry.timestamp;
+    if !(0..=max_age as i64).contains(&age) {
+        return None;
+    }
+
+    let same_place = match (cli.lat, cli.lon, &cli.location) {
+        (Some(lat), Some(lon), _) => {
+            (entry.lat - lat).abs() < 0.01 && (entry.lon - lon).abs() < 0.01
+        }
+        (_, _, Some(location)) => entry
+            .location_query
+            .as_ref()
+            .is_some_and(|q| q.to_lowercase() == location.to_lowercase()),
+        // Located by IP
+        _ => entry.location_query.is_none(),
+    };
+    let hours = entry.weather.hourly.as_ref().map_or(0, |h| h.time.len());
+    let covered =
+        entry.weather.daily.time.len() >= cli.days as usize && hours >= cli.hours as usize;
+    (same_place && covered).then_some(entry)
+}
+
 struct FreshResult {
     weather: api::WeatherData,
     city: String,
@@ -177,18 +605,10 @@ struct FreshResult {
 fn try_fresh(
     cli: &Cli,
     client: &reqwest::blocking::Client,
-    units: &api::Units,
     cache_dir: &std::path::Path,
-) -> Result<FreshResult, String> {
+) -> Result<FreshResult, FetchError> {
     let location = resolve_location(cli, client, cache_dir)?;
-    let weather = api::fetch_weather(
-        client,
-        location.lat,
-        location.lon,
-        cli.days,
-        cli.hours,
-        units,
-    )?;
+    let weather = api::fetch_weather(client, location.lat, location.lon, cli.days, cli.hours)?;
     Ok(FreshResult {
         weather,
         city: location.city,
@@ -201,7 +621,7 @@ fn resolve_location(
     cli: &Cli,
     client: &reqwest::blocking::Client,
     cache_dir: &std::path::Path,
-) -> Result<ResolvedLocation, String> {
+) -> Result<ResolvedLocation, FetchError> {
     if let (Some(lat), Some(lon)) = (cli.lat, cli.lon) {
         let city = cli
             .city_name
@@ -223,101 +643,122 @@ fn resolve_location(
         return api::geocode(client, location);
     }
 
-    api::geolocate_ip(client)
+    Ok(api::geolocate_ip(client)?)
 }
 
-fn build_output(
-    weather: &api::WeatherData,
-    city: &str,
-    cli: &Cli,
-    unit_label: &str,
-    stale: bool,
-    colors: &theme::ThemeColors,
-) -> WaybarOutput {
+struct RenderContext {
+    units: Units,
+    template: Option<TooltipTemplate>,
+    dates: DateOptions,
+    frame: FrameOptions,
+    locale: i18n::Locale,
+    colors: theme::ThemeColors,
+    renderer: Box<dyn Renderer>,
+    /// 0 for `--format`, then the `--alt-format`s
+    view: usize,
+    /// A config or template error, shown instead of the weather in
+    /// `--daemon` mode until fixed
+    config_error: Option<String>,
+}
+
+fn build_output(snapshot: &Snapshot, cli: &Cli, ctx: &RenderContext) -> BarOutput {
+    let weather = &ctx.units.convert(&snapshot.weather);
+    let city = snapshot.city.as_str();
+    icons::log_unmapped(
+        iter::once(weather.current.weather_code)
+            .chain(
+                weather
+                    .hourly
+                    .iter()
+                    .flat_map(|h| h.weather_code.iter().copied()),
+            )
+            .chain(weather.daily.weather_code.iter().copied()),
+    );
     let icon_info = icons::get_icon(
         weather.current.weather_code,
         weather.current.is_day == 1,
         &cli.icons,
     );
 
-    let current = &weather.current;
-    let today_rain = weather
-        .daily
-        .precipitation_probability_max
-        .first()
-        .copied()
-        .unwrap_or(0);
-
-    let data = FormatData {
-        icon: icon_info.icon,
-        temp: format!("{}", current.temperature_2m.round() as i32),
-        feels_like: format!(
-            "{}",
-            current
-                .apparent_temperature
-                .unwrap_or(current.temperature_2m)
-                .round() as i32
-        ),
-        humidity: format!(
-            "{}",
-            current.relative_humidity_2m.unwrap_or(0.0).round() as i32
-        ),
-        wind: format!("{}", current.wind_speed_10m.unwrap_or(0.0).round() as i32),
-        wind_dir: format::degrees_to_cardinal(current.wind_direction_10m.unwrap_or(0.0))
-            .to_string(),
-        pressure: format!("{}", current.pressure_msl.unwrap_or(0.0).round() as i32),
-        city: city.to_string(),
-        min: format!(
-            "{}",
-            weather
-                .daily
-                .temperature_2m_min
-                .first()
-                .unwrap_or(&0.0)
-                .round() as i32
-        ),
-        max: format!(
-            "{}",
-            weather
-                .daily
-                .temperature_2m_max
-                .first()
-                .unwrap_or(&0.0)
-                .round() as i32
-        ),
-        rain_chance: format!("{}", today_rain),
-        description: icon_info.description.to_string(),
+    let format_opts = FormatOptions {
+        icon_set: &cli.icons,
+        locale: &ctx.locale,
+        units: &ctx.units,
+        time_format: &cli.time_format,
+        wind_arrow: cli.wind_arrow,
+        colors: &ctx.colors,
     };
-
-    let text = format::render(&cli.format, &data);
-    let tooltip = waybar::build_tooltip(
-        city,
-        weather,
-        &cli.icons,
-        &cli.tooltip_format,
-        cli.days,
-        cli.hours,
-        unit_label,
-        colors,
-    );
-
-    let class = if stale {
-        vec!["stale".to_string()]
+    let data = FormatData::new(weather, city, &format_opts);
+    let renderer = ctx.renderer.as_ref();
+    let template = match ctx.view {
+        0 => &cli.format,
+        n => &cli.alt_format[n - 1],
+    };
+    let text = format::render(template, &data, renderer);
+    let short_text = cli
+        .short_format
+        .as_ref()
+        .map(|short| format::render(short, &data, renderer));
+    // The tooltip defaults to Nerd Font icons: they are part of the monospace
+    // font, so the box-drawing borders line up without any guesswork. Other
+    // sets are measured with their declared glyph width instead.
+    let tooltip_icons = if cli.tooltip_bar_icons {
+        &cli.icons
     } else {
-        vec![]
+        &IconSet::Nerd
     };
+    let tooltip_opts = TooltipOptions {
+        sections: &cli.tooltip_sections.0,
+        frame: &ctx.frame,
+        icon_set: tooltip_icons,
+        locale: &ctx.locale,
+        dates: &ctx.dates,
+        time_format: &cli.time_format,
+        wind_arrow: cli.wind_arrow,
+        days: cli.days,
+        hours: cli.hours,
+        units: &ctx.units,
+        template: ctx.template.as_ref(),
+    };
+    let tooltip = renderer
+        .wants_tooltip()
+        .then(|| waybar::build_tooltip(city, weather, &tooltip_opts, &ctx.colors));
+
+    let mut class = vec![icon_info.css_class.to_string()];
+    if snapshot.stale {
+        class.push("stale".to_string());
+    }
 
-    WaybarOutput {
+    let document = renderer.wants_document().then(|| {
+        let source = json::Source {
+            city,
+            lat: snapshot.lat,
+            lon: snapshot.lon,
+            updated: snapshot.updated,
+            stale: snapshot.stale,
+        };
+        let opts = json::DocumentOptions {
+            icon_set: &cli.icons,
+            locale: &ctx.locale,
+            units: &ctx.units,
+            days: cli.days,
+            hours: cli.hours,
+        };
+        json::Document::new(weather, &source, text.clone(), class.clone(), &opts)
+    });
+
+    BarOutput {
         text,
+        short_text,
         tooltip,
         class,
         alt: icon_info.css_class.to_string(),
+        urgent: icons::is_severe(weather.current.weather_code),
+        document,
+        error: None,
     }
 }
 
-fn print_and_exit(output: WaybarOutput) {
-    match serde_json::to_string(&output) {
-        Ok(json) => println!("{json}"),
-        Err(_) => println!(r#"{{"text":"?","tooltip":"serialization error","class":["error"],"alt":"error"}}"#),
-    }
+fn print_output(renderer: &dyn Renderer, output: &BarOutput) {
+    println!("{}", renderer.emit(output));
 }
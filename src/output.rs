@@ -0,0 +1,142 @@
+use std::collections::BTreeMap;
+
+use clap::ValueEnum;
+
+use crate::config::Config;
+use crate::i3bar::I3bar;
+use crate::json::{Document, Json};
+use crate::polybar::Polybar;
+use crate::terminal::Terminal;
+use crate::tmux::{Plain, Tmux};
+use crate::waybar::Waybar;
+use crate::xmobar::{Dzen2, Xmobar};
+
+/// Which bar the output is printed for.
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+pub enum OutputFormat {
+    Waybar,
+    /// Also understood by lemonbar
+    Polybar,
+    /// Also understood by swaybar
+    I3bar,
+    /// The tooltip with ANSI colors
+    Terminal,
+    Tmux,
+    /// No markup, for shell prompts
+    Plain,
+    /// The weather as structured data
+    Json,
+    Xmobar,
+    Dzen2,
+}
+
+/// One update, independent of the bar it ends up in. `text` is already in
+/// the renderer's markup.
+pub struct BarOutput {
+    pub text: String,
+    /// `--short-format`, for bars that fall back to it when space runs out
+    pub short_text: Option<String>,
+    pub tooltip: Option<String>,
+    pub class: Vec<String>,
+    pub alt: String,
+    /// Severe weather
+    pub urgent: bool,
+    /// Only built for renderers that want it
+    pub document: Option<Document>,
+    /// What went wrong, for an error output
+    pub error: Option<String>,
+}
+
+/// Markup and framing of a particular bar.
+pub trait Renderer {
+    /// Plain text made safe for the bar's markup.
+    fn escape(&self, text: &str) -> String;
+
+    /// `text` (already escaped) in `color` (`#rrggbb`).
+    fn colored(&self, color: &str, text: &str) -> String;
+
+    /// The condition icon: `icon` as the icon set wrote it for Pango, `glyph`
+    /// without any markup.
+    fn icon(&self, icon: &str, glyph: &str) -> String;
+
+    /// Whether the bar can show a tooltip at all; it is only built if so.
+    fn wants_tooltip(&self) -> bool {
+        false
+    }
+
+    /// Whether the output is the weather as data; it is only built if so.
+    fn wants_document(&self) -> bool {
+        false
+    }
+
+    /// Printed once before the first update of `--daemon` mode.
+    fn header(&self) -> Option<String> {
+        None
+    }
+
+    /// The line printed to stdout.
+    fn emit(&self, output: &BarOutput) -> String;
+}
+
+/// The color for the last of `classes` that has a rule, so `stale` beats the
+/// weather condition.
+pub fn class_color<'a>(
+    rules: &'a BTreeMap<String, String>,
+    classes: &[String],
+) -> Option<&'a String> {
+    classes.iter().rev().find_map(|class| rules.get(class))
+}
+
+/// `text` with every `close` tag that isn't escaped by a doubled `escape`
+/// replaced with `reopen`, for bars whose color tags reset to the bar's
+/// default rather than to the enclosing color.
+pub fn reopen_after_close(text: &str, escape: char, close: &str, reopen: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(ch) = rest.chars().next() {
+        let len = if rest.starts_with(close) {
+            out.push_str(reopen);
+            close.len()
+        } else if ch == escape && rest[ch.len_utf8()..].starts_with(escape) {
+            out.push_str(&rest[..2 * ch.len_utf8()]);
+            2 * ch.len_utf8()
+        } else {
+            out.push(ch);
+            ch.len_utf8()
+        };
+        rest = &rest[len..];
+    }
+    out
+}
+
+/// `daemon` selects a bar's continuous protocol where it has one.
+pub fn renderer(
+    format: OutputFormat,
+    config: &Config,
+    daemon: bool,
+) -> Result<Box<dyn Renderer>, String> {
+    Ok(match format {
+        OutputFormat::Waybar => Box::new(Waybar),
+        OutputFormat::Polybar => Box::new(Polybar::new(&config.polybar)?),
+        OutputFormat::I3bar => Box::new(I3bar::new(&config.i3bar, daemon)?),
+        OutputFormat::Terminal => Box::new(Terminal::new(daemon)),
+        OutputFormat::Tmux => Box::new(Tmux::new(&config.tmux)?),
+        OutputFormat::Plain => Box::new(Plain),
+        OutputFormat::Json => Box::new(Json),
+        OutputFormat::Xmobar => Box::new(Xmobar::new(&config.xmobar)?),
+        OutputFormat::Dzen2 => Box::new(Dzen2::new(&config.dzen2)?),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reopen_skips_escaped_tags() {
+        assert_eq!(
+            reopen_after_close("a%{F#f00}5%{F-} %%{F-}", '%', "%{F-}", "%{F#0f0}"),
+            "a%{F#f00}5%{F#0f0} %%{F-}"
+        );
+    }
+}
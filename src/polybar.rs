@@ -0,0 +1,44 @@
+use std::collections::BTreeMap;
+
+use crate::config::BarConfig;
+use crate::output::{class_color, reopen_after_close, BarOutput, Renderer};
+
+/// `%{F#rrggbb}` formatting tags on a single line, as polybar and lemonbar
+/// read them.
+pub struct Polybar {
+    class_colors: BTreeMap<String, String>,
+}
+
+impl Polybar {
+    pub fn new(config: &BarConfig) -> Result<Self, String> {
+        Ok(Self {
+            class_colors: config.class_colors("polybar")?,
+        })
+    }
+}
+
+impl Renderer for Polybar {
+    fn escape(&self, text: &str) -> String {
+        // `%` starts a tag; newlines would end the update early
+        text.replace('%', "%%").replace('\n', " ")
+    }
+
+    fn colored(&self, color: &str, text: &str) -> String {
+        format!("%{{F{color}}}{text}%{{F-}}")
+    }
+
+    fn icon(&self, _icon: &str, glyph: &str) -> String {
+        self.escape(glyph)
+    }
+
+    fn emit(&self, output: &BarOutput) -> String {
+        match class_color(&self.class_colors, &output.class) {
+            Some(color) => {
+                let reopen = format!("%{{F{color}}}");
+                let text = reopen_after_close(&output.text, '%', "%{F-}", &reopen);
+                self.colored(color, &text)
+            }
+            None => output.text.clone(),
+        }
+    }
+}
@@ -0,0 +1,146 @@
+use std::fs;
+use std::path::Path;
+
+pub enum Block {
+    Line(String),
+    Separator,
+    Hourly(Vec<String>),
+    Daily(Vec<String>),
+}
+
+pub struct TooltipTemplate {
+    pub blocks: Vec<Block>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum LoopKind {
+    Hourly,
+    Daily,
+}
+
+impl TooltipTemplate {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("failed to read tooltip template {}: {e}", path.display()))?;
+        Self::parse(&content).map_err(|e| format!("tooltip template {}: {e}", path.display()))
+    }
+
+    pub fn parse(content: &str) -> Result<Self, String> {
+        let mut blocks = Vec::new();
+        let mut open: Option<(LoopKind, usize, Vec<String>)> = None;
+
+        for (idx, line) in content.lines().enumerate() {
+            let line_no = idx + 1;
+            let trimmed = line.trim();
+
+            if let Some(name) = trimmed.strip_prefix("{#").and_then(|s| s.strip_suffix('}')) {
+                if open.is_some() {
+                    return Err(format!("line {line_no}: loops cannot be nested"));
+                }
+                open = Some((loop_kind(name, line_no)?, line_no, Vec::new()));
+                continue;
+            }
+
+            if let Some(name) = trimmed.strip_prefix("{/").and_then(|s| s.strip_suffix('}')) {
+                let kind = loop_kind(name, line_no)?;
+                match open.take() {
+                    Some((open_kind, _, body)) if open_kind == kind => {
+                        blocks.push(match kind {
+                            LoopKind::Hourly => Block::Hourly(body),
+                            LoopKind::Daily => Block::Daily(body),
+                        });
+                    }
+                    _ => return Err(format!("line {line_no}: unexpected {{/{name}}}")),
+                }
+                continue;
+            }
+
+            match open.as_mut() {
+                Some(_) if trimmed == "---" => {
+                    return Err(format!(
+                        "line {line_no}: separators cannot be inside a loop"
+                    ));
+                }
+                Some((_, _, body)) => body.push(line.to_string()),
+                None if trimmed == "---" => blocks.push(Block::Separator),
+                None => blocks.push(Block::Line(line.to_string())),
+            }
+        }
+
+        if let Some((_, line_no, _)) = open {
+            return Err(format!("line {line_no}: loop is never closed"));
+        }
+
+        Ok(Self { blocks })
+    }
+}
+
+fn loop_kind(name: &str, line_no: usize) -> Result<LoopKind, String> {
+    match name {
+        "hourly" => Ok(LoopKind::Hourly),
+        "daily" => Ok(LoopKind::Daily),
+        _ => Err(format!(
+            "line {line_no}: unknown loop '{name}' (expected hourly or daily)"
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_err(content: &str) -> String {
+        match TooltipTemplate::parse(content) {
+            Ok(_) => panic!("{content:?} parsed"),
+            Err(e) => e,
+        }
+    }
+
+    #[test]
+    fn parses_lines_separators_and_loops() {
+        let template =
+            TooltipTemplate::parse("{city}\n  ---  \n{#hourly}\n{time} {temp}\n{/hourly}").unwrap();
+        match template.blocks.as_slice() {
+            [Block::Line(city), Block::Separator, Block::Hourly(body)] => {
+                assert_eq!(city, "{city}");
+                assert_eq!(body, &["{time} {temp}"]);
+            }
+            _ => panic!("unexpected blocks"),
+        }
+    }
+
+    #[test]
+    fn rejects_nested_loops() {
+        let err = parse_err("{#daily}\n{#hourly}\n{/hourly}\n{/daily}");
+        assert_eq!(err, "line 2: loops cannot be nested");
+    }
+
+    #[test]
+    fn rejects_unclosed_and_mismatched_loops() {
+        assert_eq!(
+            parse_err("x\n{#daily}\n{day}"),
+            "line 2: loop is never closed"
+        );
+        assert_eq!(
+            parse_err("{#daily}\n{/hourly}"),
+            "line 2: unexpected {/hourly}"
+        );
+        assert_eq!(parse_err("{/daily}"), "line 1: unexpected {/daily}");
+    }
+
+    #[test]
+    fn rejects_unknown_loops() {
+        assert_eq!(
+            parse_err("{#weekly}"),
+            "line 1: unknown loop 'weekly' (expected hourly or daily)"
+        );
+    }
+
+    #[test]
+    fn rejects_separators_inside_loops() {
+        assert_eq!(
+            parse_err("{#daily}\n{day}\n---\n{/daily}"),
+            "line 3: separators cannot be inside a loop"
+        );
+    }
+}
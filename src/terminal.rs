@@ -0,0 +1,210 @@
+use std::env;
+
+use crate::output::{BarOutput, Renderer};
+use crate::theme;
+use crate::waybar::{pango_escape, tokenize, Token};
+
+/// The Waybar tooltip for a terminal, with Pango markup turned into ANSI
+/// 24-bit color escapes.
+pub struct Terminal {
+    /// Redraw in place with `--daemon` rather than scroll
+    clear: bool,
+    /// `NO_COLOR` is set
+    plain: bool,
+}
+
+impl Terminal {
+    pub fn new(daemon: bool) -> Self {
+        Self {
+            clear: daemon,
+            plain: env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty()),
+        }
+    }
+}
+
+// The bar text and the tooltip are built as Pango markup like for Waybar,
+// and only converted in `emit`.
+impl Renderer for Terminal {
+    fn escape(&self, text: &str) -> String {
+        pango_escape(text)
+    }
+
+    fn colored(&self, color: &str, text: &str) -> String {
+        format!("<span foreground='{color}'>{text}</span>")
+    }
+
+    fn icon(&self, _icon: &str, glyph: &str) -> String {
+        pango_escape(glyph)
+    }
+
+    fn wants_tooltip(&self) -> bool {
+        true
+    }
+
+    fn emit(&self, output: &BarOutput) -> String {
+        let markup = output.tooltip.as_deref().unwrap_or(&output.text);
+        let text = pango_to_ansi(markup, self.plain);
+        if self.clear {
+            format!("\x1b[H\x1b[2J{text}")
+        } else {
+            text
+        }
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+struct Style {
+    fg: Option<(u8, u8, u8)>,
+    bold: bool,
+    italic: bool,
+    underline: bool,
+}
+
+impl Style {
+    /// `self` with the attributes of an opening tag applied.
+    fn with(mut self, tag: &str) -> Self {
+        let inner = tag.trim_start_matches('<').trim_end_matches('>');
+        let (name, attrs) = inner.split_once(char::is_whitespace).unwrap_or((inner, ""));
+        match name {
+            "b" => self.bold = true,
+            "i" => self.italic = true,
+            "u" => self.underline = true,
+            "span" => {
+                for (key, value) in attributes(attrs) {
+                    match key {
+                        "foreground" | "fgcolor" | "color" => {
+                            self.fg = theme::parse_hex(value).or(self.fg);
+                        }
+                        "font_weight" | "weight" => self.bold = value == "bold",
+                        "style" | "font_style" => self.italic = value == "italic",
+                        "underline" => self.underline = value != "none",
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+        self
+    }
+
+    fn sgr(&self) -> String {
+        let mut codes = vec!["0".to_string()];
+        if self.bold {
+            codes.push("1".to_string());
+        }
+        if self.italic {
+            codes.push("3".to_string());
+        }
+        if self.underline {
+            codes.push("4".to_string());
+        }
+        if let Some((r, g, b)) = self.fg {
+            codes.push(format!("38;2;{r};{g};{b}"));
+        }
+        format!("\x1b[{}m", codes.join(";"))
+    }
+}
+
+/// `key='value'` (or double-quoted) pairs of a tag.
+fn attributes(mut s: &str) -> Vec<(&str, &str)> {
+    let mut attrs = Vec::new();
+    while let Some((key, rest)) = s.split_once('=') {
+        let rest = rest.trim_start();
+        let Some(quote) = rest.chars().next().filter(|c| *c == '\'' || *c == '"') else {
+            break;
+        };
+        let Some((value, rest)) = rest[1..].split_once(quote) else {
+            break;
+        };
+        attrs.push((key.trim(), value));
+        s = rest;
+    }
+    attrs
+}
+
+/// Converts Pango markup to text with ANSI escapes, or to plain text.
+pub fn pango_to_ansi(markup: &str, plain: bool) -> String {
+    let mut out = String::with_capacity(markup.len());
+    // Never empty: the bottom is the terminal's default style
+    let mut stack = vec![Style::default()];
+    for token in tokenize(markup) {
+        match token {
+            Token::Tag(tag) => {
+                let current = stack[stack.len() - 1];
+                if tag.starts_with("</") {
+                    if stack.len() > 1 {
+                        stack.pop();
+                    }
+                } else if !tag.ends_with("/>") {
+                    stack.push(current.with(tag));
+                }
+                if !plain {
+                    out.push_str(&stack[stack.len() - 1].sgr());
+                }
+            }
+            Token::Entity(entity) => out.push_str(&unescape(entity)),
+            Token::Char(ch) => out.push(ch),
+        }
+    }
+    if !plain {
+        out.push_str("\x1b[0m");
+    }
+    out
+}
+
+fn unescape(entity: &str) -> String {
+    let name = &entity[1..entity.len() - 1];
+    let ch = match name {
+        "amp" => Some('&'),
+        "lt" => Some('<'),
+        "gt" => Some('>'),
+        "quot" => Some('"'),
+        "apos" => Some('\''),
+        _ => match name.strip_prefix("#x").or_else(|| name.strip_prefix("#X")) {
+            Some(hex) => u32::from_str_radix(hex, 16).ok().and_then(char::from_u32),
+            None => name
+                .strip_prefix('#')
+                .and_then(|d| d.parse().ok())
+                .and_then(char::from_u32),
+        },
+    };
+    ch.map_or_else(|| entity.to_string(), String::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn attributes_reads_single_and_double_quotes() {
+        assert_eq!(
+            attributes(r#"foreground='#fff' font_weight="bold""#),
+            [("foreground", "#fff"), ("font_weight", "bold")]
+        );
+        assert_eq!(attributes("size=large"), []);
+    }
+
+    #[test]
+    fn unescape_named_and_numeric_entities() {
+        assert_eq!(unescape("&amp;"), "&");
+        assert_eq!(unescape("&#x41;"), "A");
+        assert_eq!(unescape("&#66;"), "B");
+        assert_eq!(unescape("&nbsp;"), "&nbsp;");
+        assert_eq!(unescape("&#xffffff;"), "&#xffffff;");
+    }
+
+    #[test]
+    fn pango_to_ansi_nests_styles() {
+        let markup = "<span foreground='#ff0000'>a<b>b</b></span>c &lt;";
+        assert_eq!(
+            pango_to_ansi(markup, false),
+            "\x1b[0;38;2;255;0;0ma\x1b[0;1;38;2;255;0;0mb\x1b[0;38;2;255;0;0m\x1b[0mc <\x1b[0m"
+        );
+        assert_eq!(pango_to_ansi(markup, true), "abc <");
+    }
+
+    #[test]
+    fn pango_to_ansi_ignores_stray_closing_tags() {
+        assert_eq!(pango_to_ansi("</span>x", true), "x");
+    }
+}
@@ -1,6 +1,13 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::env;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use serde::Deserialize;
+
+use crate::log;
+use crate::units::TempUnit;
 
 pub struct ThemeColors {
     pub border: String,
@@ -11,84 +18,438 @@ pub struct ThemeColors {
     pub yellow: String,
     pub orange: String,
     pub error: String,
+    /// Roles added in the config file, e.g. `cold`/`hot`
+    pub extra: BTreeMap<String, String>,
+    /// Temperature stops in display units, ascending
+    pub gradient: Vec<(f64, String)>,
+}
+
+/// Built-in colors for when no theme file is found.
+#[derive(Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum Palette {
+    /// Dark or light, following the desktop's color-scheme preference
+    Auto,
+    /// One Dark
+    Dark,
+    /// One Light
+    Light,
+    /// Okabe-Ito hues on a dark bar, distinguishable with color vision deficiencies
+    Colorblind,
 }
 
-impl Default for ThemeColors {
-    fn default() -> Self {
+impl ThemeColors {
+    /// `roles`: border, text, dim, accent, green, yellow, orange, error
+    fn from_roles(roles: [&str; 8]) -> Self {
+        let [border, text, dim, accent, green, yellow, orange, error] = roles.map(String::from);
         Self {
-            border: "#61afef".into(),
-            text: "#abb2bf".into(),
-            dim: "#5c6370".into(),
-            accent: "#61afef".into(),
-            green: "#98c379".into(),
-            yellow: "#e5c07b".into(),
-            orange: "#d19a66".into(),
-            error: "#e06c75".into(),
+            border,
+            text,
+            dim,
+            accent,
+            green,
+            yellow,
+            orange,
+            error,
+            extra: BTreeMap::new(),
+            gradient: Vec::new(),
+        }
+    }
+
+    pub fn builtin(palette: Palette) -> Self {
+        let palette = match palette {
+            Palette::Auto if prefers_light() => Palette::Light,
+            Palette::Auto => Palette::Dark,
+            other => other,
+        };
+        Self::from_roles(match palette {
+            Palette::Auto | Palette::Dark => [
+                "#61afef", "#abb2bf", "#5c6370", "#61afef", "#98c379", "#e5c07b", "#d19a66",
+                "#e06c75",
+            ],
+            Palette::Light => [
+                "#4078f2", "#383a42", "#a0a1a7", "#4078f2", "#50a14f", "#c18401", "#986801",
+                "#e45649",
+            ],
+            Palette::Colorblind => [
+                "#56b4e9", "#e0e0e0", "#7f7f7f", "#56b4e9", "#009e73", "#f0e442", "#e69f00",
+                "#d55e00",
+            ],
+        })
+    }
+}
+
+/// The freedesktop `color-scheme` setting (via the settings portal), else a
+/// `GTK_THEME` naming a light theme. Dark wins when neither says.
+fn prefers_light() -> bool {
+    // Waybar runs meteobar on every update, so a missing portal must not
+    // hold it up
+    let portal = Command::new("busctl")
+        .args([
+            "--user",
+            "--timeout=100ms",
+            "call",
+            "org.freedesktop.portal.Desktop",
+            "/org/freedesktop/portal/desktop",
+            "org.freedesktop.portal.Settings",
+            "Read",
+            "ss",
+            "org.freedesktop.appearance",
+            "color-scheme",
+        ])
+        .output()
+        .ok()
+        .filter(|out| out.status.success())
+        .and_then(|out| {
+            // "v v u 1": 0 no preference, 1 dark, 2 light
+            let text = String::from_utf8_lossy(&out.stdout).into_owned();
+            text.split_whitespace().last()?.parse::<u32>().ok()
+        });
+    log::debug(format_args!("color-scheme from portal: {portal:?}"));
+    match portal {
+        Some(1) => false,
+        Some(2) => true,
+        _ => env::var("GTK_THEME").is_ok_and(|theme| is_light_gtk_theme(&theme)),
+    }
+}
+
+/// `Adwaita`, but neither `Adwaita:dark` nor `Arc-Dark` or `Yaru-dark`.
+fn is_light_gtk_theme(name: &str) -> bool {
+    !name.is_empty() && !name.to_lowercase().contains("dark")
+}
+
+/// Supported color file formats. See the README for how each one maps onto
+/// the tooltip roles.
+#[derive(Clone, Copy)]
+enum Source {
+    Omarchy,
+    Pywal,
+    Base16,
+}
+
+impl Source {
+    fn from_extension(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("json") => Self::Pywal,
+            Some("yaml" | "yml") => Self::Base16,
+            _ => Self::Omarchy,
         }
     }
 }
 
 impl ThemeColors {
-    pub fn load() -> Self {
-        let path = dirs::config_dir()
-            .unwrap_or_else(|| PathBuf::from("~/.config"))
-            .join("omarchy/current/theme/colors.toml");
+    /// Tries `--theme`, then pywal's cache, then the active Omarchy theme,
+    /// and falls back to the built-in `palette`.
+    pub fn load(theme: Option<&Path>, palette: Palette) -> Self {
+        for (path, source) in candidates(theme) {
+            // Only a missing `--theme` file is worth mentioning
+            if !path.exists() && theme != Some(path.as_path()) {
+                continue;
+            }
+            match Self::from_file(&path, source) {
+                Ok(colors) => return colors,
+                Err(e) => log::debug(format_args!("theme {}: {e}", path.display())),
+            }
+        }
 
-        let content = match fs::read_to_string(&path) {
-            Ok(c) => c,
-            Err(_) => return Self::default(),
-        };
+        Self::builtin(palette)
+    }
 
-        let map = parse_toml_flat(&content);
-        Self::from_map(&map).unwrap_or_default()
+    /// Files `load` may read, in order of preference.
+    pub fn sources(theme: Option<&Path>) -> Vec<PathBuf> {
+        candidates(theme)
+            .into_iter()
+            .map(|(path, _)| path)
+            .collect()
     }
 
-    fn from_map(map: &HashMap<String, String>) -> Option<Self> {
-        let foreground = map.get("foreground")?;
-        let background = map.get("background")?;
-        let accent = map.get("accent")?;
-        let color1 = map.get("color1")?;
-        let color2 = map.get("color2")?;
-        let color3 = map.get("color3")?;
+    fn from_file(path: &Path, source: Source) -> Result<Self, String> {
+        let content = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        match source {
+            Source::Omarchy => Self::from_map(&parse_toml_colors(&content)?),
+            Source::Pywal => Self::from_pywal(&content),
+            Source::Base16 => Self::from_base16(parse_yaml_flat(&content)),
+        }
+    }
+
+    fn from_map(map: &HashMap<String, String>) -> Result<Self, String> {
+        let get = |key| lookup(map, key);
+        let foreground = get("foreground")?;
+        let background = get("background")?;
+        let accent = get("accent")?;
+        let color1 = get("color1")?;
+
+        Ok(Self {
+            border: accent.clone(),
+            accent,
+            dim: blend_hex(&foreground, &background, 0.5),
+            text: foreground,
+            green: get("color2")?,
+            yellow: get("color3")?,
+            orange: color1.clone(),
+            error: color1,
+            extra: BTreeMap::new(),
+            gradient: Vec::new(),
+        })
+    }
+
+    fn from_pywal(content: &str) -> Result<Self, String> {
+        #[derive(Deserialize)]
+        struct Wal {
+            special: HashMap<String, String>,
+            colors: HashMap<String, String>,
+        }
+
+        let wal: Wal = serde_json::from_str(content).map_err(|e| e.to_string())?;
+        let get = |key| lookup(&wal.colors, key);
+        let accent = get("color4")?;
+        let color1 = get("color1")?;
 
-        Some(Self {
+        Ok(Self {
             border: accent.clone(),
-            accent: accent.clone(),
-            text: foreground.clone(),
-            dim: blend_hex(foreground, background, 0.5),
-            green: color2.clone(),
-            yellow: color3.clone(),
+            accent,
+            text: lookup(&wal.special, "foreground")?,
+            dim: get("color8")?,
+            green: get("color2")?,
+            yellow: get("color3")?,
             orange: color1.clone(),
-            error: color1.clone(),
+            error: color1,
+            extra: BTreeMap::new(),
+            gradient: Vec::new(),
+        })
+    }
+
+    // base24 keeps the base16 meaning of base00-base0F, so both share this.
+    fn from_base16(mut map: HashMap<String, String>) -> Result<Self, String> {
+        // Schemes usually leave out the `#`
+        for value in map.values_mut() {
+            if !value.starts_with('#') {
+                value.insert(0, '#');
+            }
+        }
+        let get = |key| lookup(&map, key);
+        let accent = get("base0D")?;
+
+        Ok(Self {
+            border: accent.clone(),
+            accent,
+            text: get("base05")?,
+            dim: get("base03")?,
+            green: get("base0B")?,
+            yellow: get("base0A")?,
+            orange: get("base09")?,
+            error: get("base08")?,
+            extra: BTreeMap::new(),
+            gradient: Vec::new(),
         })
     }
 }
 
-fn parse_toml_flat(content: &str) -> HashMap<String, String> {
+impl ThemeColors {
+    pub fn role(&self, name: &str) -> Option<&str> {
+        match name {
+            "border" => Some(&self.border),
+            "text" => Some(&self.text),
+            "dim" => Some(&self.dim),
+            "accent" => Some(&self.accent),
+            "green" => Some(&self.green),
+            "yellow" => Some(&self.yellow),
+            "orange" => Some(&self.orange),
+            "error" => Some(&self.error),
+            _ => self.extra.get(name).map(String::as_str),
+        }
+    }
+
+    /// Applies the config file's `[colors]` table on top of the theme.
+    pub fn apply_overrides(&mut self, overrides: &BTreeMap<String, String>) -> Result<(), String> {
+        for (name, value) in overrides {
+            let Some(color) = normalize(value) else {
+                return Err(format!(
+                    "colors.{name}: '{value}' is not a color like #rrggbb, #rgb or rgb(r, g, b)"
+                ));
+            };
+            let slot = match name.as_str() {
+                "border" => &mut self.border,
+                "text" => &mut self.text,
+                "dim" => &mut self.dim,
+                "accent" => &mut self.accent,
+                "green" => &mut self.green,
+                "yellow" => &mut self.yellow,
+                "orange" => &mut self.orange,
+                "error" => &mut self.error,
+                _ if is_role_name(name) => self.extra.entry(name.clone()).or_default(),
+                _ => {
+                    return Err(format!(
+                        "colors.{name}: role names may only use a-z, 0-9, '-' and '_'"
+                    ))
+                }
+            };
+            *slot = color;
+        }
+        Ok(())
+    }
+}
+
+impl ThemeColors {
+    /// Builds the temperature gradient from the config's `[temp_gradient]`
+    /// table, or from the theme's accent, green and error roles if it is empty.
+    pub fn set_gradient(
+        &mut self,
+        stops: &BTreeMap<String, String>,
+        unit: TempUnit,
+    ) -> Result<(), String> {
+        let mut gradient = Vec::with_capacity(stops.len());
+        for (key, value) in stops {
+            let celsius = key
+                .parse::<f64>()
+                .ok()
+                .filter(|t| t.is_finite())
+                .ok_or_else(|| format!("temp_gradient: '{key}' is not a temperature"))?;
+            let color = match normalize(value) {
+                Some(color) => color,
+                None => self.role(value).map(str::to_string).ok_or_else(|| {
+                    format!(
+                        "temp_gradient.{key}: '{value}' is neither a hex color nor a color role"
+                    )
+                })?,
+            };
+            gradient.push((celsius, color));
+        }
+        if gradient.is_empty() {
+            gradient = vec![
+                (-10.0, self.accent.clone()),
+                (15.0, self.green.clone()),
+                (30.0, self.error.clone()),
+            ];
+        }
+        gradient.sort_by(|a, b| a.0.total_cmp(&b.0));
+        self.gradient = gradient
+            .into_iter()
+            .map(|(celsius, color)| (unit.convert(celsius), color))
+            .collect();
+        Ok(())
+    }
+
+    /// Color for `temp` (display units), interpolated between the two
+    /// nearest stops.
+    pub fn temp_color(&self, temp: f64) -> String {
+        let (Some(first), Some(last)) = (self.gradient.first(), self.gradient.last()) else {
+            return self.text.clone();
+        };
+        if temp <= first.0 {
+            return first.1.clone();
+        }
+        for pair in self.gradient.windows(2) {
+            let ((t0, c0), (t1, c1)) = (&pair[0], &pair[1]);
+            if temp <= *t1 {
+                if t1 <= t0 {
+                    return c1.clone();
+                }
+                return blend_hex(c0, c1, ((temp - t0) / (t1 - t0)) as f32);
+            }
+        }
+        last.1.clone()
+    }
+}
+
+fn is_role_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_')
+}
+
+fn candidates(theme: Option<&Path>) -> Vec<(PathBuf, Source)> {
+    let mut list = Vec::new();
+    if let Some(path) = theme {
+        list.push((path.to_path_buf(), Source::from_extension(path)));
+    }
+    if let Some(cache) = dirs::cache_dir() {
+        list.push((cache.join("wal/colors.json"), Source::Pywal));
+    }
+    list.push((
+        dirs::config_dir()
+            .unwrap_or_else(|| PathBuf::from("~/.config"))
+            .join("omarchy/current/theme/colors.toml"),
+        Source::Omarchy,
+    ));
+    list
+}
+
+fn lookup(map: &HashMap<String, String>, key: &str) -> Result<String, String> {
+    let value = map
+        .get(key)
+        .ok_or_else(|| format!("missing color '{key}'"))?;
+    normalize(value).ok_or_else(|| format!("color '{key}': '{value}' is not a color"))
+}
+
+// Omarchy's colors.toml keeps its keys at the top level; other themes put
+// them under a `[colors]` table.
+fn parse_toml_colors(content: &str) -> Result<HashMap<String, String>, String> {
+    let mut table: toml::Table = toml::from_str(content).map_err(|e| {
+        let line = e
+            .span()
+            .map_or(1, |span| content[..span.start].matches('\n').count() + 1);
+        format!("line {line}: {}", e.message())
+    })?;
+    if let Some(toml::Value::Table(colors)) = table.remove("colors") {
+        table = colors;
+    }
+    Ok(table
+        .into_iter()
+        .filter_map(|(key, value)| match value {
+            toml::Value::String(s) => Some((key, s)),
+            _ => None,
+        })
+        .collect())
+}
+
+// Enough YAML for base16/base24 schemes: `key: value` pairs at any depth,
+// optionally quoted, with trailing comments.
+fn parse_yaml_flat(content: &str) -> HashMap<String, String> {
     let mut map = HashMap::new();
     for line in content.lines() {
         let line = line.trim();
         if line.is_empty() || line.starts_with('#') {
             continue;
         }
-        if let Some((key, value)) = line.split_once('=') {
-            let key = key.trim().to_string();
-            let value = value.trim().trim_matches('"').to_string();
-            map.insert(key, value);
+        if let Some((key, value)) = line.split_once(':') {
+            let value = value.trim();
+            let value = match value.strip_prefix(['"', '\'']) {
+                Some(rest) => rest.split(['"', '\'']).next().unwrap_or(""),
+                None => value.split_whitespace().next().unwrap_or(""),
+            };
+            map.insert(key.trim().to_string(), value.to_string());
         }
     }
     map
 }
 
-fn parse_hex(hex: &str) -> Option<(u8, u8, u8)> {
-    let hex = hex.strip_prefix('#')?;
-    if hex.len() != 6 {
+/// Accepts `#rrggbb`, `#rgb` and `rgb(r, g, b)`.
+pub fn parse_hex(color: &str) -> Option<(u8, u8, u8)> {
+    let color = color.trim();
+    if let Some(args) = color.strip_prefix("rgb(").and_then(|s| s.strip_suffix(')')) {
+        let mut parts = args.split(',').map(|p| p.trim().parse::<u8>().ok());
+        let rgb = (parts.next()??, parts.next()??, parts.next()??);
+        return parts.next().is_none().then_some(rgb);
+    }
+
+    let hex = color.strip_prefix('#')?;
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
         return None;
     }
-    let r = u8::from_str_radix(&hex[0..2], 16).ok()?;
-    let g = u8::from_str_radix(&hex[2..4], 16).ok()?;
-    let b = u8::from_str_radix(&hex[4..6], 16).ok()?;
-    Some((r, g, b))
+    let channel = |range: std::ops::Range<usize>| u8::from_str_radix(&hex[range], 16).ok();
+    // `#abc` is `#aabbcc`
+    let short = |i: usize| channel(i..i + 1).map(|v| v * 17);
+    match hex.len() {
+        3 => Some((short(0)?, short(1)?, short(2)?)),
+        6 => Some((channel(0..2)?, channel(2..4)?, channel(4..6)?)),
+        _ => None,
+    }
+}
+
+/// Any accepted color notation as `#rrggbb`, which Pango understands.
+pub fn normalize(color: &str) -> Option<String> {
+    parse_hex(color).map(|(r, g, b)| format!("#{r:02x}{g:02x}{b:02x}"))
 }
 
 fn blend_hex(c1: &str, c2: &str, ratio: f32) -> String {
@@ -99,3 +460,76 @@ fn blend_hex(c1: &str, c2: &str, ratio: f32) -> String {
     };
     format!("#{:02x}{:02x}{:02x}", blend(r1, r2), blend(g1, g2), blend(b1, b2))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gtk_theme_is_light_only_without_dark_in_the_name() {
+        for light in ["Adwaita", "Arc", "Yaru", "Breeze"] {
+            assert!(is_light_gtk_theme(light), "{light}");
+        }
+        for dark in ["", "Adwaita:dark", "Adwaita-dark", "Arc-Dark", "Yaru-dark"] {
+            assert!(!is_light_gtk_theme(dark), "{dark}");
+        }
+    }
+
+    #[test]
+    fn yaml_flat_reads_nested_quoted_and_commented_values() {
+        let scheme = r#"
+system: "base16"
+name: 'One Dark'
+palette:
+  base00: "282c34" # bg
+  base08: e06c75
+# base09: ignored
+"#;
+        let map = parse_yaml_flat(scheme);
+        assert_eq!(map["name"], "One Dark");
+        assert_eq!(map["base00"], "282c34");
+        assert_eq!(map["base08"], "e06c75");
+        assert!(!map.contains_key("base09"));
+        assert_eq!(map["palette"], "");
+    }
+
+    #[test]
+    fn parse_hex_accepts_long_short_and_rgb_notation() {
+        assert_eq!(parse_hex("#61afef"), Some((0x61, 0xaf, 0xef)));
+        assert_eq!(parse_hex(" #FFF "), Some((255, 255, 255)));
+        assert_eq!(parse_hex("rgb(97, 175,239)"), Some((97, 175, 239)));
+    }
+
+    #[test]
+    fn parse_hex_rejects_malformed_colors() {
+        for color in [
+            "61afef",
+            "#61afe",
+            "#61afeg",
+            "#ééé",
+            "rgb(1, 2)",
+            "rgb(1, 2, 3, 4)",
+            "rgb(256, 0, 0)",
+        ] {
+            assert_eq!(parse_hex(color), None, "{color}");
+        }
+    }
+
+    #[test]
+    fn normalize_gives_lowercase_rrggbb() {
+        assert_eq!(normalize("#ABC").as_deref(), Some("#aabbcc"));
+        assert_eq!(normalize("rgb(0, 128, 255)").as_deref(), Some("#0080ff"));
+        assert_eq!(normalize("blue"), None);
+    }
+
+    #[test]
+    fn toml_colors_read_the_colors_table_or_the_top_level() {
+        let nested = parse_toml_colors("[colors]\naccent = \"#fff\"\nsize = 3\n").unwrap();
+        assert_eq!(nested["accent"], "#fff");
+        assert!(!nested.contains_key("size"));
+        let flat = parse_toml_colors("accent = \"#000\"").unwrap();
+        assert_eq!(flat["accent"], "#000");
+        let err = parse_toml_colors("accent = \n").unwrap_err();
+        assert!(err.starts_with("line 1:"), "{err}");
+    }
+}
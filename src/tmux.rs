@@ -0,0 +1,64 @@
+use std::collections::BTreeMap;
+
+use crate::config::BarConfig;
+use crate::output::{class_color, reopen_after_close, BarOutput, Renderer};
+
+/// `#[fg=#rrggbb]` styles for tmux's `status-left`/`status-right`.
+pub struct Tmux {
+    class_colors: BTreeMap<String, String>,
+}
+
+impl Tmux {
+    pub fn new(config: &BarConfig) -> Result<Self, String> {
+        Ok(Self {
+            class_colors: config.class_colors("tmux")?,
+        })
+    }
+}
+
+impl Renderer for Tmux {
+    fn escape(&self, text: &str) -> String {
+        // `#` starts a style or a format; newlines would end the line early
+        text.replace('#', "##").replace('\n', " ")
+    }
+
+    fn colored(&self, color: &str, text: &str) -> String {
+        format!("#[fg={color}]{text}#[fg=default]")
+    }
+
+    fn icon(&self, _icon: &str, glyph: &str) -> String {
+        self.escape(glyph)
+    }
+
+    fn emit(&self, output: &BarOutput) -> String {
+        match class_color(&self.class_colors, &output.class) {
+            Some(color) => {
+                let reopen = format!("#[fg={color}]");
+                let text = reopen_after_close(&output.text, '#', "#[fg=default]", &reopen);
+                self.colored(color, &text)
+            }
+            None => output.text.clone(),
+        }
+    }
+}
+
+/// No markup at all, for shell prompts.
+pub struct Plain;
+
+impl Renderer for Plain {
+    fn escape(&self, text: &str) -> String {
+        text.replace('\n', " ")
+    }
+
+    fn colored(&self, _color: &str, text: &str) -> String {
+        text.to_string()
+    }
+
+    fn icon(&self, _icon: &str, glyph: &str) -> String {
+        glyph.to_string()
+    }
+
+    fn emit(&self, output: &BarOutput) -> String {
+        output.text.clone()
+    }
+}
@@ -0,0 +1,272 @@
+use clap::ValueEnum;
+
+use crate::api::WeatherData;
+
+// Open-Meteo is always queried in metric units (°C, km/h, hPa, mm); every
+// conversion to the selected display units happens here.
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum TempUnit {
+    #[value(name = "C")]
+    Celsius,
+    #[value(name = "F")]
+    Fahrenheit,
+    #[value(name = "K")]
+    Kelvin,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum WindUnit {
+    #[value(name = "km/h", alias = "kmh")]
+    Kmh,
+    #[value(name = "m/s", alias = "ms")]
+    Ms,
+    Mph,
+    Kn,
+    Beaufort,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum PressureUnit {
+    #[value(name = "hPa", alias = "hpa")]
+    Hpa,
+    #[value(name = "inHg", alias = "inhg")]
+    InHg,
+    #[value(name = "mmHg", alias = "mmhg")]
+    MmHg,
+    #[value(name = "kPa", alias = "kpa")]
+    Kpa,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum PrecipUnit {
+    Mm,
+    Inch,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum UnitPreset {
+    Metric,
+    Imperial,
+}
+
+pub struct Units {
+    pub temp: TempUnit,
+    pub wind: WindUnit,
+    pub pressure: PressureUnit,
+    pub precip: PrecipUnit,
+}
+
+impl Units {
+    pub fn preset(preset: UnitPreset) -> Self {
+        match preset {
+            UnitPreset::Metric => Self {
+                temp: TempUnit::Celsius,
+                wind: WindUnit::Kmh,
+                pressure: PressureUnit::Hpa,
+                precip: PrecipUnit::Mm,
+            },
+            UnitPreset::Imperial => Self {
+                temp: TempUnit::Fahrenheit,
+                wind: WindUnit::Mph,
+                pressure: PressureUnit::Hpa,
+                precip: PrecipUnit::Mm,
+            },
+        }
+    }
+
+    /// Returns a copy of metric `data` expressed in the selected units.
+    pub fn convert(&self, data: &WeatherData) -> WeatherData {
+        let mut data = data.clone();
+        let temp = |v: &mut f64| *v = self.temp.convert(*v);
+        let wind = |v: &mut f64| *v = self.wind.convert(*v);
+
+        let current = &mut data.current;
+        temp(&mut current.temperature_2m);
+        if let Some(t) = current.apparent_temperature.as_mut() {
+            temp(t);
+        }
+        if let Some(w) = current.wind_speed_10m.as_mut() {
+            wind(w);
+        }
+        if let Some(w) = current.wind_gusts_10m.as_mut() {
+            wind(w);
+        }
+        if let Some(p) = current.pressure_msl.as_mut() {
+            *p = self.pressure.convert(*p);
+        }
+        if let Some(p) = current.precipitation.as_mut() {
+            *p = self.precip.convert(*p);
+        }
+
+        data.daily.temperature_2m_max.iter_mut().for_each(temp);
+        data.daily.temperature_2m_min.iter_mut().for_each(temp);
+        data.daily.wind_speed_10m_max.iter_mut().for_each(wind);
+        if let Some(hourly) = data.hourly.as_mut() {
+            hourly.temperature_2m.iter_mut().for_each(temp);
+        }
+        data
+    }
+}
+
+impl TempUnit {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Celsius => "°C",
+            Self::Fahrenheit => "°F",
+            Self::Kelvin => "K",
+        }
+    }
+
+    pub fn convert(self, c: f64) -> f64 {
+        match self {
+            Self::Celsius => c,
+            Self::Fahrenheit => c * 9.0 / 5.0 + 32.0,
+            Self::Kelvin => c + 273.15,
+        }
+    }
+}
+
+// Upper bounds (exclusive, km/h) of Beaufort forces 0-11
+const BEAUFORT_KMH: [f64; 12] = [
+    1.0, 6.0, 12.0, 20.0, 29.0, 39.0, 50.0, 62.0, 75.0, 89.0, 103.0, 118.0,
+];
+
+impl WindUnit {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Kmh => "km/h",
+            Self::Ms => "m/s",
+            Self::Mph => "mph",
+            Self::Kn => "kn",
+            Self::Beaufort => "Bft",
+        }
+    }
+
+    pub fn format(self, value: f64) -> String {
+        match self {
+            Self::Ms => format!("{value:.1}"),
+            _ => format!("{}", value.round() as i32),
+        }
+    }
+
+    /// Beaufort force of a speed already expressed in this unit.
+    pub fn beaufort(self, value: f64) -> u8 {
+        let kmh = match self {
+            Self::Kmh => value,
+            Self::Ms => value * 3.6,
+            Self::Mph => value * 1.609344,
+            Self::Kn => value * 1.852,
+            Self::Beaufort => return value as u8,
+        };
+        beaufort(kmh)
+    }
+
+    fn convert(self, kmh: f64) -> f64 {
+        match self {
+            Self::Kmh => kmh,
+            Self::Ms => kmh / 3.6,
+            Self::Mph => kmh / 1.609344,
+            Self::Kn => kmh / 1.852,
+            Self::Beaufort => beaufort(kmh) as f64,
+        }
+    }
+}
+
+fn beaufort(kmh: f64) -> u8 {
+    BEAUFORT_KMH
+        .iter()
+        .position(|&max| kmh < max)
+        .unwrap_or(BEAUFORT_KMH.len()) as u8
+}
+
+impl PressureUnit {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Hpa => "hPa",
+            Self::InHg => "inHg",
+            Self::MmHg => "mmHg",
+            Self::Kpa => "kPa",
+        }
+    }
+
+    pub fn format(self, value: f64) -> String {
+        match self {
+            Self::InHg => format!("{value:.2}"),
+            Self::Kpa => format!("{value:.1}"),
+            Self::Hpa | Self::MmHg => format!("{}", value.round() as i32),
+        }
+    }
+
+    fn convert(self, hpa: f64) -> f64 {
+        match self {
+            Self::Hpa => hpa,
+            Self::InHg => hpa * 0.029_53,
+            Self::MmHg => hpa * 0.750_062,
+            Self::Kpa => hpa / 10.0,
+        }
+    }
+}
+
+impl PrecipUnit {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Mm => "mm",
+            Self::Inch => "in",
+        }
+    }
+
+    pub fn format(self, value: f64) -> String {
+        match self {
+            Self::Mm => format!("{value:.1}"),
+            Self::Inch => format!("{value:.2}"),
+        }
+    }
+
+    fn convert(self, mm: f64) -> f64 {
+        match self {
+            Self::Mm => mm,
+            Self::Inch => mm / 25.4,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 0.01
+    }
+
+    #[test]
+    fn converts_from_metric() {
+        assert!(close(TempUnit::Fahrenheit.convert(20.0), 68.0));
+        assert!(close(TempUnit::Kelvin.convert(-10.0), 263.15));
+        assert!(close(WindUnit::Ms.convert(36.0), 10.0));
+        assert!(close(WindUnit::Mph.convert(100.0), 62.14));
+        assert!(close(WindUnit::Kn.convert(18.52), 10.0));
+        assert!(close(PressureUnit::InHg.convert(1013.0), 29.91));
+        assert!(close(PressureUnit::MmHg.convert(1013.0), 759.81));
+        assert!(close(PressureUnit::Kpa.convert(1013.0), 101.3));
+        assert!(close(PrecipUnit::Inch.convert(25.4), 1.0));
+    }
+
+    #[test]
+    fn beaufort_boundaries() {
+        assert_eq!(beaufort(0.99), 0);
+        assert_eq!(beaufort(1.0), 1);
+        assert_eq!(beaufort(117.9), 11);
+        assert_eq!(beaufort(118.0), 12);
+        assert_eq!(WindUnit::Ms.beaufort(10.0), 5);
+    }
+
+    #[test]
+    fn imperial_preset_keeps_hpa_and_mm() {
+        let units = Units::preset(UnitPreset::Imperial);
+        assert_eq!(units.temp.label(), "°F");
+        assert_eq!(units.wind.label(), "mph");
+        assert_eq!(units.pressure.label(), "hPa");
+        assert_eq!(units.precip.label(), "mm");
+    }
+}
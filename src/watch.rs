@@ -0,0 +1,92 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::thread;
+
+use inotify::{Inotify, WatchDescriptor, WatchMask, Watches};
+
+use crate::log;
+
+// Editors and theme switchers replace files rather than rewrite them, so the
+// directories are watched instead of the files themselves.
+const MASK: WatchMask = WatchMask::CLOSE_WRITE
+    .union(WatchMask::MOVED_TO)
+    .union(WatchMask::CREATE)
+    .union(WatchMask::DELETE);
+
+/// Calls `notify` whenever one of `files` is written, replaced or removed,
+/// until it returns false. A symlinked parent directory (Omarchy's
+/// `current/theme`) is followed, and re-watched when the link is swapped.
+/// Nothing happens if inotify is unavailable.
+pub fn spawn(files: Vec<PathBuf>, notify: impl Fn() -> bool + Send + 'static) {
+    let inotify = match Inotify::init() {
+        Ok(i) => i,
+        Err(e) => {
+            log::debug(format_args!("theme watching disabled: {e}"));
+            return;
+        }
+    };
+
+    let mut watcher = Watcher {
+        watches: inotify.watches(),
+        dirs: HashMap::new(),
+        files,
+    };
+    for file in watcher.files.clone() {
+        let Some(dir) = file.parent() else { continue };
+        watcher.watch(dir);
+        if dir.is_symlink() {
+            if let Some(link_dir) = dir.parent() {
+                watcher.watch(link_dir);
+            }
+        }
+    }
+
+    thread::spawn(move || watcher.run(inotify, notify));
+}
+
+struct Watcher {
+    watches: Watches,
+    dirs: HashMap<WatchDescriptor, PathBuf>,
+    files: Vec<PathBuf>,
+}
+
+impl Watcher {
+    fn watch(&mut self, dir: &Path) {
+        match self.watches.add(dir, MASK) {
+            Ok(wd) => {
+                self.dirs.insert(wd, dir.to_path_buf());
+            }
+            Err(e) => log::debug(format_args!("cannot watch {}: {e}", dir.display())),
+        }
+    }
+
+    fn run(mut self, mut inotify: Inotify, notify: impl Fn() -> bool) {
+        let mut buffer = [0; 4096];
+        loop {
+            let events = match inotify.read_events_blocking(&mut buffer) {
+                Ok(events) => events,
+                Err(e) => {
+                    log::debug(format_args!("theme watching stopped: {e}"));
+                    return;
+                }
+            };
+            let changed: Vec<PathBuf> = events
+                .filter_map(|event| Some(self.dirs.get(&event.wd)?.join(event.name?)))
+                .collect();
+
+            let mut relevant = false;
+            for path in changed {
+                // Either a file itself or a directory on the way to one
+                if self.files.iter().any(|f| f.starts_with(&path)) {
+                    relevant = true;
+                    if path.is_dir() {
+                        self.watch(&path);
+                    }
+                }
+            }
+            if relevant && !notify() {
+                return;
+            }
+        }
+    }
+}
@@ -1,10 +1,19 @@
+use std::borrow::Cow;
+
+use chrono::{Datelike, NaiveDate, Weekday};
 use serde::Serialize;
-use unicode_width::UnicodeWidthStr;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
 use crate::api::{DailyForecast, HourlyForecast, WeatherData};
-use crate::format::degrees_to_cardinal;
-use crate::icons::{get_icon, IconSet};
+use crate::format::{
+    degrees_to_cardinal, render_with, FormatData, FormatOptions, TimeFormat, WindArrow,
+};
+use crate::i18n::Locale;
+use crate::icons::{get_icon, rain_icon, IconSet};
+use crate::output::{BarOutput, Renderer};
+use crate::template::{Block, TooltipTemplate};
 use crate::theme::ThemeColors;
+use crate::units::Units;
 
 #[derive(Serialize)]
 pub struct WaybarOutput {
@@ -14,14 +23,114 @@ pub struct WaybarOutput {
     pub alt: String,
 }
 
-#[derive(Clone, clap::ValueEnum)]
-pub enum TooltipFormat {
-    Days,
-    Hours,
-    Both,
+#[derive(Clone, Copy, PartialEq)]
+pub enum TooltipSection {
+    Current,
+    Stats,
+    Sun,
+    Hourly,
+    Daily,
+}
+
+#[derive(Clone)]
+pub struct TooltipSections(pub Vec<TooltipSection>);
+
+impl std::str::FromStr for TooltipSections {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        use TooltipSection::*;
+
+        let mut sections = Vec::new();
+        for name in s.split(',').map(str::trim).filter(|n| !n.is_empty()) {
+            let expanded: &[TooltipSection] = match name {
+                "current" => &[Current],
+                "stats" => &[Stats],
+                "sun" => &[Sun],
+                "hourly" => &[Hourly],
+                "daily" => &[Daily],
+                // Shorthands for the old --tooltip-format values
+                "days" => &[Current, Stats, Daily],
+                "hours" => &[Current, Stats, Hourly],
+                "both" => &[Current, Stats, Hourly, Daily],
+                "alerts" | "air" => {
+                    return Err(format!("section '{name}' has no data source yet"));
+                }
+                _ => {
+                    return Err(format!(
+                        "unknown section '{name}' (expected current, stats, sun, hourly, daily, days, hours or both)"
+                    ));
+                }
+            };
+            for section in expanded {
+                if !sections.contains(section) {
+                    sections.push(*section);
+                }
+            }
+        }
+
+        if sections.is_empty() {
+            return Err("at least one tooltip section is required".into());
+        }
+        Ok(Self(sections))
+    }
+}
+
+pub struct DateOptions {
+    pub format: String,
+    pub relative: bool,
+    pub highlight_weekends: bool,
+}
+
+pub struct TooltipOptions<'a> {
+    pub sections: &'a [TooltipSection],
+    pub frame: &'a FrameOptions,
+    pub icon_set: &'a IconSet,
+    pub locale: &'a Locale,
+    pub dates: &'a DateOptions,
+    pub time_format: &'a TimeFormat,
+    pub wind_arrow: WindArrow,
+    pub days: u8,
+    pub hours: u8,
+    pub units: &'a Units,
+    pub template: Option<&'a TooltipTemplate>,
 }
 
-const MIN_WIDTH: usize = 20;
+/// Waybar's JSON with Pango markup; the only bar with a tooltip.
+pub struct Waybar;
+
+impl Renderer for Waybar {
+    fn escape(&self, text: &str) -> String {
+        pango_escape(text)
+    }
+
+    fn colored(&self, color: &str, text: &str) -> String {
+        fg(color, text)
+    }
+
+    fn icon(&self, icon: &str, _glyph: &str) -> String {
+        icon.to_string()
+    }
+
+    fn wants_tooltip(&self) -> bool {
+        true
+    }
+
+    fn emit(&self, output: &BarOutput) -> String {
+        let output = WaybarOutput {
+            text: output.text.clone(),
+            tooltip: output.tooltip.clone().unwrap_or_default(),
+            class: output.class.clone(),
+            alt: output.alt.clone(),
+        };
+        serde_json::to_string(&output).unwrap_or_else(|_| {
+            r#"{"text":"?","tooltip":"serialization error","class":["error"],"alt":"error"}"#
+                .to_string()
+        })
+    }
+}
+
+pub const DEFAULT_MIN_WIDTH: usize = 20;
 
 pub fn pango_escape(s: &str) -> String {
     s.replace('&', "&amp;")
@@ -37,33 +146,169 @@ fn bold_fg(color: &str, text: &str) -> String {
     format!("<span font_weight='bold' foreground='{color}'>{text}</span>")
 }
 
-fn border_line(content: &str, width: usize, border_color: &str) -> String {
-    let pad = width.saturating_sub(visible_len(content));
-    let right_pad = " ".repeat(pad);
-    format!(
-        "{} {content}{right_pad} {}",
-        fg(border_color, "│"),
-        fg(border_color, "│")
-    )
+#[derive(Clone, Copy, clap::ValueEnum)]
+pub enum BorderStyle {
+    /// Rounded box-drawing corners
+    Rounded,
+    /// Square box-drawing corners
+    Square,
+    /// Double box-drawing lines
+    Double,
+    /// Heavy box-drawing lines
+    Heavy,
+    /// Plain ASCII (`+-|`)
+    Ascii,
+    /// No border at all
+    None,
+}
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+pub enum Overflow {
+    /// Cut long lines short with an ellipsis
+    Truncate,
+    /// Continue long lines on the next row
+    Wrap,
 }
 
-fn separator(width: usize, border_color: &str, dim_color: &str) -> String {
-    border_line(&fg(dim_color, &"─".repeat(width)), width, border_color)
+pub struct FrameOptions {
+    pub border: BorderStyle,
+    pub min_width: usize,
+    pub max_width: Option<usize>,
+    pub overflow: Overflow,
 }
 
-fn empty_line(width: usize, border_color: &str) -> String {
-    border_line(&" ".repeat(width), width, border_color)
+impl FrameOptions {
+    fn width_for(&self, items: &[&str], icons: &IconSet) -> usize {
+        let widest = items
+            .iter()
+            .map(|c| visible_len(c, icons))
+            .max()
+            .unwrap_or(0);
+        let width = widest.max(self.min_width);
+        match self.max_width {
+            Some(max) => width.min(max),
+            None => width,
+        }
+    }
+}
+
+struct BorderChars {
+    top_left: char,
+    top_right: char,
+    bottom_left: char,
+    bottom_right: char,
+    horizontal: char,
+    vertical: char,
+    rule: char,
 }
 
-fn top_border(width: usize, border_color: &str) -> String {
-    fg(border_color, &format!("╭{}╮", "─".repeat(width + 2)))
+impl BorderStyle {
+    fn chars(self) -> Option<BorderChars> {
+        let (corners, horizontal, vertical, rule) = match self {
+            BorderStyle::Rounded => (['╭', '╮', '╰', '╯'], '─', '│', '─'),
+            BorderStyle::Square => (['┌', '┐', '└', '┘'], '─', '│', '─'),
+            BorderStyle::Double => (['╔', '╗', '╚', '╝'], '═', '║', '─'),
+            BorderStyle::Heavy => (['┏', '┓', '┗', '┛'], '━', '┃', '─'),
+            BorderStyle::Ascii => (['+', '+', '+', '+'], '-', '|', '-'),
+            BorderStyle::None => return None,
+        };
+        Some(BorderChars {
+            top_left: corners[0],
+            top_right: corners[1],
+            bottom_left: corners[2],
+            bottom_right: corners[3],
+            horizontal,
+            vertical,
+            rule,
+        })
+    }
 }
 
-fn bottom_border(width: usize, border_color: &str) -> String {
-    fg(border_color, &format!("╰{}╯", "─".repeat(width + 2)))
+/// Draws the box around tooltip content once its width is known.
+struct Frame<'a> {
+    width: usize,
+    chars: Option<BorderChars>,
+    overflow: Overflow,
+    /// Measures the icons in the content
+    icons: &'a IconSet,
+    border_color: &'a str,
+    dim_color: &'a str,
+}
+
+impl<'a> Frame<'a> {
+    fn fit(
+        items: &[&str],
+        opts: &FrameOptions,
+        icons: &'a IconSet,
+        colors: &'a ThemeColors,
+    ) -> Self {
+        Self {
+            width: opts.width_for(items, icons),
+            chars: opts.border.chars(),
+            overflow: opts.overflow,
+            icons,
+            border_color: &colors.border,
+            dim_color: &colors.dim,
+        }
+    }
+
+    /// Appends `content` as one or more bordered lines, shortening or
+    /// wrapping it when it is wider than the frame.
+    fn push(&self, lines: &mut Vec<String>, content: &str) {
+        if visible_len(content, self.icons) <= self.width {
+            lines.push(self.line(content));
+            return;
+        }
+        match self.overflow {
+            Overflow::Truncate => {
+                lines.push(self.line(&truncate_markup(content, self.width, self.icons)));
+            }
+            Overflow::Wrap => {
+                for part in wrap_markup(content, self.width, self.icons) {
+                    lines.push(self.line(&part));
+                }
+            }
+        }
+    }
+
+    fn line(&self, content: &str) -> String {
+        let pad = self.width.saturating_sub(visible_len(content, self.icons));
+        let content = content.replace(ICON_CELL, "");
+        let Some(c) = &self.chars else {
+            return content;
+        };
+        let right_pad = " ".repeat(pad);
+        let side = fg(self.border_color, &c.vertical.to_string());
+        format!("{side} {content}{right_pad} {side}")
+    }
+
+    fn separator(&self) -> String {
+        // Without a border, a plain dashed rule keeps the tooltip ASCII.
+        let rule = self.chars.as_ref().map_or('-', |c| c.rule);
+        self.line(&fg(self.dim_color, &rule.to_string().repeat(self.width)))
+    }
+
+    fn empty(&self) -> String {
+        self.line(&" ".repeat(self.width))
+    }
+
+    fn top(&self) -> Option<String> {
+        let c = self.chars.as_ref()?;
+        Some(self.horizontal_edge(c.top_left, c.horizontal, c.top_right))
+    }
+
+    fn bottom(&self) -> Option<String> {
+        let c = self.chars.as_ref()?;
+        Some(self.horizontal_edge(c.bottom_left, c.horizontal, c.bottom_right))
+    }
+
+    fn horizontal_edge(&self, left: char, fill: char, right: char) -> String {
+        let fill = fill.to_string().repeat(self.width + 2);
+        fg(self.border_color, &format!("{left}{fill}{right}"))
+    }
 }
 
-fn visible_len(s: &str) -> usize {
+fn visible_len(s: &str, icons: &IconSet) -> usize {
     let mut plain = String::with_capacity(s.len());
     let mut in_tag = false;
     let mut in_entity = false;
@@ -89,47 +334,258 @@ fn visible_len(s: &str) -> usize {
         }
     }
 
-    plain.width()
+    text_width(&plain, icons)
 }
 
-fn rain_color<'a>(pct: u8, colors: &'a ThemeColors) -> &'a str {
-    if pct >= 60 {
-        &colors.accent
-    } else if pct >= 30 {
-        &colors.yellow
+/// Stands in for the cells an icon takes beyond the width of its characters;
+/// counted when measuring and dropped when a line is drawn.
+const ICON_CELL: char = '\u{FDD0}';
+
+/// `icon` followed by enough `ICON_CELL`s to make it `width` cells wide, when
+/// its set declares a width.
+fn sized_icon(icon: &str, width: Option<usize>, icons: &IconSet) -> String {
+    let missing = width.map_or(0, |w| w.saturating_sub(visible_len(icon, icons)));
+    let mut out = icon.to_string();
+    out.extend(std::iter::repeat_n(ICON_CELL, missing));
+    out
+}
+
+/// The declared width of `ch`, if it is an icon glyph or an `ICON_CELL`.
+fn declared_width(ch: char, icons: &IconSet) -> Option<usize> {
+    if ch == ICON_CELL {
+        Some(1)
     } else {
-        &colors.green
+        icons.glyph_width(ch)
+    }
+}
+
+/// Like `UnicodeWidthStr::width`, but glyphs of `icons` count with the width
+/// the set declares rather than as a single narrow cell.
+fn text_width(plain: &str, icons: &IconSet) -> usize {
+    let mut width = 0;
+    let mut run_start = 0;
+    for (i, ch) in plain.char_indices() {
+        if let Some(w) = declared_width(ch, icons) {
+            width += plain[run_start..i].width() + w;
+            run_start = i + ch.len_utf8();
+        }
+    }
+    width + plain[run_start..].width()
+}
+
+/// A whole tag, a whole entity or a character of Pango markup.
+pub enum Token<'a> {
+    Tag(&'a str),
+    Entity(&'a str),
+    Char(char),
+}
+
+impl Token<'_> {
+    fn width(&self, icons: &IconSet) -> usize {
+        match self {
+            Token::Tag(_) => 0,
+            Token::Entity(_) => 1,
+            Token::Char(ch) => {
+                declared_width(*ch, icons).unwrap_or_else(|| ch.width().unwrap_or(0))
+            }
+        }
+    }
+
+    fn push_to(&self, out: &mut String) {
+        match self {
+            Token::Tag(t) | Token::Entity(t) => out.push_str(t),
+            Token::Char(ch) => out.push(*ch),
+        }
     }
 }
 
-fn rain_icon(icon_set: &IconSet) -> &'static str {
-    match icon_set {
-        IconSet::Nerd => "󰖗",
-        IconSet::Weather => "\u{e318}",
-        IconSet::Emoji => "💧",
-        IconSet::Fontawesome => "\u{f73d}",
+pub fn tokenize(s: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut rest = s;
+    while let Some(ch) = rest.chars().next() {
+        let end = match ch {
+            '<' => rest.find('>').map(|i| i + 1),
+            '&' => rest.find(';').map(|i| i + 1),
+            _ => None,
+        };
+        match end {
+            Some(end) if ch == '<' => tokens.push(Token::Tag(&rest[..end])),
+            Some(end) => tokens.push(Token::Entity(&rest[..end])),
+            None => tokens.push(Token::Char(ch)),
+        }
+        rest = &rest[end.unwrap_or(ch.len_utf8())..];
     }
+    tokens
+}
+
+fn closing_tag(open: &str) -> String {
+    let name: String = open[1..]
+        .chars()
+        .take_while(|c| c.is_alphanumeric())
+        .collect();
+    format!("</{name}>")
 }
 
-fn content_width(items: &[&str]) -> usize {
-    items
+fn truncate_markup(s: &str, max: usize, icons: &IconSet) -> String {
+    let budget = max.saturating_sub(1);
+    let mut out = String::with_capacity(s.len());
+    let mut used = 0;
+    let mut cut = false;
+
+    for token in tokenize(s) {
+        // Tags are always kept so every span that was opened is closed again.
+        if let Token::Tag(_) = token {
+            token.push_to(&mut out);
+            continue;
+        }
+        if cut {
+            continue;
+        }
+        let w = token.width(icons);
+        if used + w > budget {
+            out.push('…');
+            cut = true;
+            continue;
+        }
+        token.push_to(&mut out);
+        used += w;
+    }
+    out
+}
+
+fn wrap_markup(s: &str, width: usize, icons: &IconSet) -> Vec<String> {
+    let tokens = tokenize(s);
+    // Continuation lines keep the indentation of the first one.
+    let indent = tokens
         .iter()
-        .map(|c| visible_len(c))
-        .max()
-        .unwrap_or(MIN_WIDTH)
-        .max(MIN_WIDTH)
+        .take_while(|t| matches!(t, Token::Char(' ') | Token::Tag(_)))
+        .filter(|t| matches!(t, Token::Char(' ')))
+        .count()
+        .min(width / 2);
+
+    let mut wrapper = Wrapper {
+        width,
+        indent,
+        lines: Vec::new(),
+        line: String::new(),
+        line_width: 0,
+        has_words: false,
+        open_tags: Vec::new(),
+    };
+
+    let mut word: Vec<&Token> = Vec::new();
+    let mut word_width = 0;
+    let mut spaces = 0;
+    for token in &tokens {
+        if let Token::Char(' ') = token {
+            if !word.is_empty() {
+                wrapper.push_word(spaces, &word, word_width);
+                // Tag-only words (e.g. an empty span) don't consume spacing.
+                if word_width > 0 {
+                    spaces = 0;
+                }
+                word.clear();
+                word_width = 0;
+            }
+            if wrapper.lines.is_empty() && !wrapper.has_words {
+                wrapper.line.push(' ');
+                wrapper.line_width += 1;
+            } else {
+                spaces += 1;
+            }
+            continue;
+        }
+        // Words longer than a whole line are broken wherever they overflow.
+        if word_width > 0 && word_width + token.width(icons) > width.saturating_sub(indent) {
+            wrapper.push_word(spaces, &word, word_width);
+            word.clear();
+            word_width = 0;
+            spaces = 0;
+        }
+        word_width += token.width(icons);
+        word.push(token);
+    }
+    if !word.is_empty() {
+        wrapper.push_word(spaces, &word, word_width);
+    }
+    wrapper.finish()
+}
+
+struct Wrapper<'a> {
+    width: usize,
+    indent: usize,
+    lines: Vec<String>,
+    line: String,
+    line_width: usize,
+    has_words: bool,
+    open_tags: Vec<&'a str>,
+}
+
+impl<'a> Wrapper<'a> {
+    fn push_word(&mut self, spaces: usize, word: &[&Token<'a>], word_width: usize) {
+        if word_width > 0 && self.has_words {
+            if self.line_width + spaces + word_width > self.width {
+                self.break_line();
+            } else {
+                self.line.push_str(&" ".repeat(spaces));
+                self.line_width += spaces;
+            }
+        }
+        for token in word {
+            match token {
+                Token::Tag(t) if t.starts_with("</") => {
+                    self.open_tags.pop();
+                }
+                Token::Tag(t) => self.open_tags.push(t),
+                _ => {}
+            }
+            token.push_to(&mut self.line);
+        }
+        self.line_width += word_width;
+        self.has_words |= word_width > 0;
+    }
+
+    fn break_line(&mut self) {
+        for tag in self.open_tags.iter().rev() {
+            self.line.push_str(&closing_tag(tag));
+        }
+        let mut next = " ".repeat(self.indent);
+        for tag in &self.open_tags {
+            next.push_str(tag);
+        }
+        self.lines.push(std::mem::replace(&mut self.line, next));
+        self.line_width = self.indent;
+        self.has_words = false;
+    }
+
+    fn finish(mut self) -> Vec<String> {
+        self.lines.push(self.line);
+        self.lines
+    }
+}
+
+fn rain_color(pct: u8, colors: &ThemeColors) -> &str {
+    if pct >= 60 {
+        &colors.accent
+    } else if pct >= 30 {
+        &colors.yellow
+    } else {
+        &colors.green
+    }
 }
 
 pub fn build_tooltip(
     city: &str,
     data: &WeatherData,
-    _icon_set: &IconSet,
-    tooltip_format: &TooltipFormat,
-    days: u8,
-    hours: u8,
-    unit_label: &str,
+    opts: &TooltipOptions,
     colors: &ThemeColors,
 ) -> String {
+    if let Some(template) = opts.template {
+        return build_template_tooltip(template, city, data, opts, colors);
+    }
+
+    let (hours, units) = (opts.hours, opts.units);
+    let unit_label = units.temp.label();
     let current = &data.current;
     let temp = current.temperature_2m.round() as i32;
     let feels = current
@@ -140,132 +596,351 @@ pub fn build_tooltip(
         .relative_humidity_2m
         .map(|v| v.round() as i32)
         .unwrap_or(0);
-    let wind = current.wind_speed_10m.unwrap_or(0.0).round() as i32;
+    let wind = units.wind.format(current.wind_speed_10m.unwrap_or(0.0));
     let wind_dir = degrees_to_cardinal(current.wind_direction_10m.unwrap_or(0.0));
-    let pressure = current.pressure_msl.unwrap_or(0.0).round() as i32;
-    // Tooltip always uses Nerd Font icons for consistent monospace alignment.
-    // Pango renders emoji from a separate font with different glyph metrics,
-    // breaking box-drawing border alignment. Nerd icons are part of the
-    // monospace font and have consistent width. The --icons flag still
-    // controls the bar text via the `text` field.
-    let tooltip_icons = &IconSet::Nerd;
+    let pressure = units.pressure.format(current.pressure_msl.unwrap_or(0.0));
+    let tooltip_icons = opts.icon_set;
     let icon_info = get_icon(current.weather_code, current.is_day == 1, tooltip_icons);
-    let speed_unit = if unit_label == "°F" { "mph" } else { "km/h" };
 
-    let (c_border, c_text, c_dim, c_accent) =
-        (&colors.border, &colors.text, &colors.dim, &colors.accent);
+    let (c_text, c_dim, c_accent) = (&colors.text, &colors.dim, &colors.accent);
 
     // Phase 1: Build all content strings (without borders)
     let title_raw = pango_escape(city);
-    let title_vlen = visible_len(&title_raw);
+    let title_vlen = visible_len(&title_raw, tooltip_icons);
 
     let temp_line = format!(
         "  {} {}  {}  {} {}",
-        fg(c_text, &icon_info.icon),
+        fg(
+            c_text,
+            &sized_icon(&icon_info.icon, icon_info.width, tooltip_icons)
+        ),
         bold_fg(c_accent, &format!("{temp}{unit_label}")),
-        fg(c_dim, icon_info.description),
-        fg(c_dim, "feels"),
+        fg(c_dim, &pango_escape(opts.locale.description(&icon_info))),
+        fg(c_dim, &pango_escape(opts.locale.text("feels"))),
         fg(c_dim, &format!("{feels}{unit_label}"))
     );
 
+    let gusts_str = current
+        .wind_gusts_10m
+        .map(|g| {
+            format!(
+                "  {} {}",
+                fg(c_dim, &pango_escape(opts.locale.text("gusts"))),
+                fg(c_text, &units.wind.format(g)),
+            )
+        })
+        .unwrap_or_default();
+
     let stats1 = format!(
-        "  {}  {}{}   {}  {} {} {}",
+        "  {}  {}{}   {}  {} {} {}{}",
         fg(c_accent, "󰖎"),
         fg(c_text, &humidity.to_string()),
         fg(c_dim, "%"),
         fg(c_accent, "󰖝"),
-        fg(c_text, &wind.to_string()),
-        fg(c_dim, speed_unit),
+        fg(c_text, &wind),
+        fg(c_dim, units.wind.label()),
         fg(c_dim, wind_dir),
+        gusts_str,
     );
 
     let stats2 = format!(
         "  {}  {} {}",
         fg(c_accent, "󰖏"),
-        fg(c_text, &pressure.to_string()),
-        fg(c_dim, "hPa"),
+        fg(c_text, &pressure),
+        fg(c_dim, units.pressure.label()),
     );
 
-    let show_days = matches!(tooltip_format, TooltipFormat::Days | TooltipFormat::Both);
-    let show_hours = matches!(tooltip_format, TooltipFormat::Hours | TooltipFormat::Both);
-
-    let hourly_lines = if show_hours && hours > 0 {
-        data.hourly
-            .as_ref()
-            .map(|h| build_hourly_lines(h, hours, tooltip_icons, unit_label, colors))
-            .unwrap_or_default()
-    } else {
-        Vec::new()
-    };
+    let today_sun = data.daily.sunrise.first().zip(data.daily.sunset.first());
+    let sun_line = today_sun.map(|(rise, set)| {
+        format!(
+            "  {}  {}   {}  {}",
+            fg(c_accent, "󰖜"),
+            fg(c_text, &pango_escape(&opts.time_format.format(rise))),
+            fg(c_accent, "󰖛"),
+            fg(c_text, &pango_escape(&opts.time_format.format(set))),
+        )
+    });
 
-    let daily_lines = if show_days {
-        build_daily_lines(&data.daily, days, tooltip_icons, unit_label, colors)
-    } else {
-        Vec::new()
-    };
+    let mut blocks: Vec<TooltipBlock> = Vec::new();
+    for section in opts.sections {
+        let block = match section {
+            TooltipSection::Current => TooltipBlock::compact(vec![temp_line.clone()]),
+            TooltipSection::Stats => TooltipBlock::compact(vec![stats1.clone(), stats2.clone()]),
+            TooltipSection::Sun => TooltipBlock::compact(sun_line.iter().cloned().collect()),
+            TooltipSection::Hourly if hours > 0 => TooltipBlock::headed(
+                opts.locale.text("hourly"),
+                data.hourly
+                    .as_ref()
+                    .map(|h| build_hourly_lines(h, opts, colors))
+                    .unwrap_or_default(),
+            ),
+            TooltipSection::Hourly => continue,
+            TooltipSection::Daily => TooltipBlock::headed(
+                opts.locale.text("forecast"),
+                build_daily_lines(&data.daily, local_today(data), opts, colors),
+            ),
+        };
+        if !block.rows.is_empty() {
+            blocks.push(block);
+        }
+    }
 
     // Phase 2: Calculate dynamic width from content
-    let mut measurable: Vec<&str> = vec![&temp_line, &stats1, &stats2];
-    for line in &hourly_lines {
-        measurable.push(line);
-    }
-    for line in &daily_lines {
-        measurable.push(line);
-    }
-    let width = content_width(&measurable).max(title_vlen);
+    let mut measurable: Vec<&str> = blocks
+        .iter()
+        .flat_map(|b| b.rows.iter().map(String::as_str))
+        .collect();
+    measurable.push(&title_raw);
+    let frame = Frame::fit(&measurable, opts.frame, opts.icon_set, colors);
 
     // Phase 3: Build bordered output
     let mut lines = Vec::new();
-    lines.push(top_border(width, c_border));
+    lines.extend(frame.top());
 
     let title_pango = bold_fg(c_accent, &title_raw);
-    let left_pad = (width.saturating_sub(title_vlen)) / 2;
+    let left_pad = (frame.width.saturating_sub(title_vlen)) / 2;
     let padded_title = format!("{}{}", " ".repeat(left_pad), title_pango);
-    lines.push(border_line(&padded_title, width, c_border));
+    frame.push(&mut lines, &padded_title);
+
+    let mut prev_compact = false;
+    for block in &blocks {
+        // Consecutive compact blocks (current conditions, stats, sun) are only
+        // spaced apart; everything else gets its own separator.
+        let compact = block.heading.is_none();
+        if compact && prev_compact {
+            lines.push(frame.empty());
+        } else {
+            lines.push(frame.separator());
+        }
+        if let Some(heading) = block.heading {
+            let heading = pango_escape(heading);
+            frame.push(&mut lines, &bold_fg(c_text, &format!("  {heading}")));
+            lines.push(frame.empty());
+        }
+        for row in &block.rows {
+            frame.push(&mut lines, row);
+        }
+        prev_compact = compact;
+    }
 
-    lines.push(separator(width, c_border, c_dim));
-    lines.push(border_line(&temp_line, width, c_border));
-    lines.push(empty_line(width, c_border));
-    lines.push(border_line(&stats1, width, c_border));
-    lines.push(border_line(&stats2, width, c_border));
+    lines.extend(frame.bottom());
+    lines.join("\n")
+}
+
+struct TooltipBlock<'a> {
+    heading: Option<&'a str>,
+    rows: Vec<String>,
+}
 
-    if !hourly_lines.is_empty() {
-        lines.push(separator(width, c_border, c_dim));
-        lines.push(border_line(&bold_fg(c_text, "  Hourly"), width, c_border));
-        lines.push(empty_line(width, c_border));
-        for line in &hourly_lines {
-            lines.push(border_line(line, width, c_border));
+impl<'a> TooltipBlock<'a> {
+    fn compact(rows: Vec<String>) -> Self {
+        Self {
+            heading: None,
+            rows,
         }
     }
 
-    if !daily_lines.is_empty() {
-        lines.push(separator(width, c_border, c_dim));
-        lines.push(border_line(&bold_fg(c_text, "  Forecast"), width, c_border));
-        lines.push(empty_line(width, c_border));
-        for line in &daily_lines {
-            lines.push(border_line(line, width, c_border));
+    fn headed(heading: &'a str, rows: Vec<String>) -> Self {
+        Self {
+            heading: Some(heading),
+            rows,
+        }
+    }
+}
+
+fn build_template_tooltip(
+    template: &TooltipTemplate,
+    city: &str,
+    data: &WeatherData,
+    opts: &TooltipOptions,
+    colors: &ThemeColors,
+) -> String {
+    let format_opts = FormatOptions {
+        icon_set: opts.icon_set,
+        locale: opts.locale,
+        units: opts.units,
+        time_format: opts.time_format,
+        wind_arrow: opts.wind_arrow,
+        colors,
+    };
+    let base = FormatData::new(data, city, &format_opts);
+    let hourly = data
+        .hourly
+        .as_ref()
+        .map(|h| hourly_entries(h, opts))
+        .unwrap_or_default();
+    let daily = daily_entries(&data.daily, local_today(data), opts);
+    let resolve_base = |key: &str| template_value(key, &base, opts, colors);
+
+    // `None` marks a separator, which is only drawn once the width is known.
+    let mut rows: Vec<Option<String>> = Vec::new();
+    for block in &template.blocks {
+        match block {
+            Block::Line(line) => rows.push(Some(render_with(line, resolve_base))),
+            Block::Separator => rows.push(None),
+            Block::Hourly(body) => render_loop(body, &hourly, &resolve_base, &mut rows),
+            Block::Daily(body) => render_loop(body, &daily, &resolve_base, &mut rows),
         }
     }
 
-    lines.push(bottom_border(width, c_border));
+    let measurable: Vec<&str> = rows.iter().flatten().map(String::as_str).collect();
+    let frame = Frame::fit(&measurable, opts.frame, opts.icon_set, colors);
+
+    let mut lines = Vec::with_capacity(rows.len() + 2);
+    lines.extend(frame.top());
+    for row in &rows {
+        match row {
+            Some(content) => frame.push(&mut lines, content),
+            None => lines.push(frame.separator()),
+        }
+    }
+    lines.extend(frame.bottom());
     lines.join("\n")
 }
 
+type TemplateEntry = Vec<(&'static str, String)>;
+
+fn render_loop<'a, F>(
+    body: &[String],
+    entries: &'a [TemplateEntry],
+    resolve_base: &F,
+    rows: &mut Vec<Option<String>>,
+) where
+    F: Fn(&str) -> Option<Cow<'a, str>>,
+{
+    for entry in entries {
+        for line in body {
+            let resolve = |key: &str| {
+                entry
+                    .iter()
+                    .find(|(k, _)| *k == key)
+                    .map(|(_, v)| Cow::Borrowed(v.as_str()))
+                    .or_else(|| resolve_base(key))
+            };
+            rows.push(Some(render_with(line, resolve)));
+        }
+    }
+}
+
+fn template_value<'a>(
+    key: &str,
+    base: &'a FormatData,
+    opts: &TooltipOptions,
+    colors: &'a ThemeColors,
+) -> Option<Cow<'a, str>> {
+    if key == "icon" {
+        let icon = sized_icon(&base.icon, base.icon_width, opts.icon_set);
+        return Some(Cow::Owned(icon));
+    }
+    match key.strip_prefix("color.") {
+        Some(role) => colors.role(role).map(Cow::Borrowed),
+        None => base.markup(key, &Waybar).map(Cow::Owned),
+    }
+}
+
+fn hourly_entries(hourly: &HourlyForecast, opts: &TooltipOptions) -> Vec<TemplateEntry> {
+    let count = (opts.hours as usize).min(hourly.time.len());
+    (0..count)
+        .filter_map(|i| {
+            // Caches from before hourly `is_day` was fetched
+            let is_day = hourly.is_day.get(i).is_none_or(|d| *d == 1);
+            let icon_info = get_icon(*hourly.weather_code.get(i)?, is_day, opts.icon_set);
+            let temp = *hourly.temperature_2m.get(i)?;
+            let description = pango_escape(opts.locale.description(&icon_info));
+            let rain = hourly
+                .precipitation_probability
+                .get(i)
+                .copied()
+                .unwrap_or(0);
+            Some(vec![
+                (
+                    "time",
+                    pango_escape(&opts.time_format.format(&hourly.time[i])),
+                ),
+                (
+                    "icon",
+                    sized_icon(&icon_info.icon, icon_info.width, opts.icon_set),
+                ),
+                ("temp", (temp.round() as i32).to_string()),
+                ("rain_chance", rain.to_string()),
+                ("description", description),
+            ])
+        })
+        .collect()
+}
+
+fn daily_entries(
+    daily: &DailyForecast,
+    today: NaiveDate,
+    opts: &TooltipOptions,
+) -> Vec<TemplateEntry> {
+    let count = (opts.days as usize).min(daily.time.len());
+    (0..count)
+        .filter_map(|i| {
+            let icon_info = get_icon(*daily.weather_code.get(i)?, true, opts.icon_set);
+            let (min, max) = (
+                *daily.temperature_2m_min.get(i)?,
+                *daily.temperature_2m_max.get(i)?,
+            );
+            let description = pango_escape(opts.locale.description(&icon_info));
+            let rain = daily
+                .precipitation_probability_max
+                .get(i)
+                .copied()
+                .unwrap_or(0);
+            Some(vec![
+                (
+                    "day",
+                    pango_escape(&day_label(&daily.time[i], today, opts).text),
+                ),
+                (
+                    "icon",
+                    sized_icon(&icon_info.icon, icon_info.width, opts.icon_set),
+                ),
+                ("min", (min.round() as i32).to_string()),
+                ("max", (max.round() as i32).to_string()),
+                ("rain_chance", rain.to_string()),
+                ("description", description),
+            ])
+        })
+        .collect()
+}
+
 fn build_daily_lines(
     daily: &DailyForecast,
-    days: u8,
-    icon_set: &IconSet,
-    unit_label: &str,
+    today: NaiveDate,
+    opts: &TooltipOptions,
     colors: &ThemeColors,
 ) -> Vec<String> {
-    let count = (days as usize).min(daily.time.len());
+    let (icon_set, unit_label) = (opts.icon_set, opts.units.temp.label());
+    let count = (opts.days as usize).min(daily.time.len());
+    let labels: Vec<DayLabel> = daily.time[..count]
+        .iter()
+        .map(|d| day_label(d, today, opts))
+        .collect();
+    let label_width = labels
+        .iter()
+        .map(|l| visible_len(&l.text, icon_set))
+        .max()
+        .unwrap_or(0)
+        .max(6);
     let mut lines = Vec::new();
 
-    for i in 0..count {
-        let day_name = short_day_name(&daily.time[i]);
-        let icon_info = get_icon(daily.weather_code[i], true, icon_set);
-        let min = daily.temperature_2m_min[i].round() as i32;
-        let max = daily.temperature_2m_max[i].round() as i32;
+    for (i, label) in labels.iter().enumerate() {
+        let day_name = pad_right(&pango_escape(&label.text), label_width, icon_set);
+        let day_color = if label.weekend && opts.dates.highlight_weekends {
+            &colors.accent
+        } else {
+            &colors.text
+        };
+        let (Some(&code), Some(&min), Some(&max)) = (
+            daily.weather_code.get(i),
+            daily.temperature_2m_min.get(i),
+            daily.temperature_2m_max.get(i),
+        ) else {
+            continue;
+        };
+        let icon_info = get_icon(code, true, icon_set);
         let rain = daily
             .precipitation_probability_max
             .get(i)
@@ -274,9 +949,10 @@ fn build_daily_lines(
 
         let rain_str = if rain > 0 {
             let rc = rain_color(rain, colors);
+            let (icon, width) = rain_icon(icon_set);
             format!(
                 "  {}  {}",
-                fg(rc, rain_icon(icon_set)),
+                fg(rc, &sized_icon(&icon, width, icon_set)),
                 fg(rc, &format!("{rain}%"))
             )
         } else {
@@ -285,11 +961,14 @@ fn build_daily_lines(
 
         let row = format!(
             "  {} {}  {} {}/{}{}{}",
-            fg(&colors.text, &icon_info.icon),
-            bold_fg(&colors.text, &format!("{:<6}", day_name)),
+            fg(
+                &colors.text,
+                &sized_icon(&icon_info.icon, icon_info.width, icon_set)
+            ),
+            bold_fg(day_color, &day_name),
             fg(&colors.dim, ""),
-            fg(&colors.green, &min.to_string()),
-            fg(&colors.orange, &max.to_string()),
+            fg(&colors.temp_color(min), &(min.round() as i32).to_string()),
+            fg(&colors.temp_color(max), &(max.round() as i32).to_string()),
             fg(&colors.dim, unit_label),
             rain_str,
         );
@@ -300,28 +979,31 @@ fn build_daily_lines(
 
 fn build_hourly_lines(
     hourly: &HourlyForecast,
-    hours: u8,
-    icon_set: &IconSet,
-    unit_label: &str,
+    opts: &TooltipOptions,
     colors: &ThemeColors,
 ) -> Vec<String> {
-    let count = (hours as usize).min(hourly.time.len());
+    let (icon_set, unit_label) = (opts.icon_set, opts.units.temp.label());
+    let count = (opts.hours as usize).min(hourly.time.len());
+    let labels: Vec<String> = hourly.time[..count]
+        .iter()
+        .map(|t| pango_escape(&opts.time_format.format(t)))
+        .collect();
+    let label_width = labels
+        .iter()
+        .map(|l| visible_len(l, icon_set))
+        .max()
+        .unwrap_or(0);
     let mut lines = Vec::new();
 
-    for i in 0..count {
-        let time_str = hourly
-            .time
-            .get(i)
-            .map(|t| {
-                t.split('T')
-                    .nth(1)
-                    .unwrap_or("??:??")
-                    .get(..5)
-                    .unwrap_or("??:??")
-            })
-            .unwrap_or("??:??");
-        let icon_info = get_icon(hourly.weather_code[i], true, icon_set);
-        let temp = hourly.temperature_2m[i].round() as i32;
+    for (i, label) in labels.iter().enumerate() {
+        let time_str = pad_right(label, label_width, icon_set);
+        let (Some(&code), Some(&temp)) = (hourly.weather_code.get(i), hourly.temperature_2m.get(i))
+        else {
+            continue;
+        };
+        // Caches from before hourly `is_day` was fetched
+        let is_day = hourly.is_day.get(i).is_none_or(|d| *d == 1);
+        let icon_info = get_icon(code, is_day, icon_set);
         let rain = hourly
             .precipitation_probability
             .get(i)
@@ -336,10 +1018,13 @@ fn build_hourly_lines(
 
         let row = format!(
             "  {} {}  {} {}{}{}",
-            fg(&colors.dim, time_str),
-            fg(&colors.text, &icon_info.icon),
+            fg(&colors.dim, &time_str),
+            fg(
+                &colors.text,
+                &sized_icon(&icon_info.icon, icon_info.width, icon_set)
+            ),
             fg(&colors.dim, ""),
-            fg(&colors.text, &temp.to_string()),
+            fg(&colors.temp_color(temp), &(temp.round() as i32).to_string()),
             fg(&colors.dim, unit_label),
             rain_str,
         );
@@ -348,32 +1033,172 @@ fn build_hourly_lines(
     lines
 }
 
-fn short_day_name(date_str: &str) -> String {
-    if let Ok(date) = chrono::NaiveDate::parse_from_str(date_str, "%Y-%m-%d") {
-        date.format("%a %d").to_string()
-    } else {
-        date_str.to_string()
+struct DayLabel {
+    text: String,
+    weekend: bool,
+}
+
+fn day_label(date_str: &str, today: NaiveDate, opts: &TooltipOptions) -> DayLabel {
+    let Ok(date) = NaiveDate::parse_from_str(date_str, "%Y-%m-%d") else {
+        return DayLabel {
+            text: date_str.to_string(),
+            weekend: false,
+        };
+    };
+    let text = match (date - today).num_days() {
+        0 if opts.dates.relative => opts.locale.text("today").to_string(),
+        1 if opts.dates.relative => opts.locale.text("tomorrow").to_string(),
+        _ => opts.locale.format_date(date, &opts.dates.format),
+    };
+    DayLabel {
+        text,
+        weekend: matches!(date.weekday(), Weekday::Sat | Weekday::Sun),
     }
 }
 
-pub fn error_output(message: &str, colors: &ThemeColors) -> WaybarOutput {
-    let header = bold_fg(&colors.error, "  meteobar error");
-    let body = fg(&colors.dim, &format!("  {}", pango_escape(message)));
+// "Today" for the forecast location, which may be in another timezone
+fn local_today(data: &WeatherData) -> NaiveDate {
+    let offset = chrono::Duration::seconds(data.utc_offset_seconds.into());
+    (chrono::Utc::now() + offset).date_naive()
+}
 
-    let width = content_width(&[&header, &body]);
+// Width-aware `{:<width}`, so CJK day names line up too
+fn pad_right(s: &str, width: usize, icons: &IconSet) -> String {
+    let pad = width.saturating_sub(visible_len(s, icons));
+    format!("{s}{}", " ".repeat(pad))
+}
 
-    let lines = [
-        top_border(width, &colors.border),
-        border_line(&header, width, &colors.border),
-        separator(width, &colors.border, &colors.dim),
-        border_line(&body, width, &colors.border),
-        bottom_border(width, &colors.border),
-    ];
+/// What an error output reports, for its translated header.
+#[derive(Clone, Copy)]
+pub enum ErrorKind {
+    /// A weather or location service failed, with no cache to fall back on
+    Network,
+    /// `--location` names no known place
+    Location,
+    /// The cache exists but couldn't be used
+    Cache,
+    /// The config file, theme or template is broken
+    Config,
+}
 
-    WaybarOutput {
+impl ErrorKind {
+    fn catalog_key(self) -> &'static str {
+        match self {
+            Self::Network => "error_network",
+            Self::Location => "error_location",
+            Self::Cache => "error_cache",
+            Self::Config => "error",
+        }
+    }
+}
+
+/// The translated `kind` as the header, and `message` as it is below.
+pub fn error_output(
+    kind: ErrorKind,
+    message: &str,
+    frame_opts: &FrameOptions,
+    locale: &Locale,
+    colors: &ThemeColors,
+) -> BarOutput {
+    let header = bold_fg(
+        &colors.error,
+        &format!("  {}", pango_escape(locale.text(kind.catalog_key()))),
+    );
+    let body = fg(&colors.dim, &format!("  {}", pango_escape(message)));
+
+    let frame = Frame::fit(&[&header, &body], frame_opts, &IconSet::Nerd, colors);
+    let mut lines = Vec::new();
+    lines.extend(frame.top());
+    frame.push(&mut lines, &header);
+    lines.push(frame.separator());
+    frame.push(&mut lines, &body);
+    lines.extend(frame.bottom());
+
+    BarOutput {
         text: "?".to_string(),
-        tooltip: lines.join("\n"),
+        short_text: None,
+        tooltip: Some(lines.join("\n")),
         class: vec!["error".to_string()],
         alt: "error".to_string(),
+        urgent: false,
+        document: None,
+        error: Some(message.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn truncate_keeps_tags_balanced() {
+        let line = "<span foreground='#fff'>Berlin &amp; Brandenburg</span>";
+        assert_eq!(
+            truncate_markup(line, 10, &IconSet::Nerd),
+            "<span foreground='#fff'>Berlin &amp; …</span>"
+        );
+        assert_eq!(
+            visible_len(&truncate_markup(line, 10, &IconSet::Nerd), &IconSet::Nerd),
+            10
+        );
+    }
+
+    #[test]
+    fn truncate_counts_wide_glyphs() {
+        assert_eq!(
+            truncate_markup("日本語のテキスト", 7, &IconSet::Nerd),
+            "日本語…"
+        );
+        // A Weather Icons glyph takes two cells only with that set
+        assert_eq!(
+            truncate_markup("\u{e30d}abcd", 4, &IconSet::Weather),
+            "\u{e30d}a…"
+        );
+        assert_eq!(
+            truncate_markup("\u{e30d}abcd", 4, &IconSet::Nerd),
+            "\u{e30d}ab…"
+        );
+    }
+
+    #[test]
+    fn wrap_breaks_between_words_and_reopens_tags() {
+        let lines = wrap_markup("  <b>one two three</b>", 9, &IconSet::Nerd);
+        assert_eq!(lines, ["  <b>one two</b>", "  <b>three</b>"]);
+    }
+
+    #[test]
+    fn wrap_splits_words_longer_than_a_line() {
+        let lines = wrap_markup("abcdefghij", 4, &IconSet::Nerd);
+        assert_eq!(lines, ["abcd", "efgh", "ij"]);
+    }
+
+    #[test]
+    fn custom_width_applies_to_whole_icons() {
+        let path = std::env::temp_dir().join("meteobar-ascii-icons.toml");
+        std::fs::write(&path, "width = 2\nrain = \"R\"\n[codes]\n2 = \"C\"\n").unwrap();
+        let icons = crate::icons::parse_icon_set(&format!("custom:{}", path.display())).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        // The same letters in ordinary text stay one cell wide
+        assert_eq!(visible_len("°C", &icons), 2);
+        let info = get_icon(2, true, &icons);
+        let icon = sized_icon(&info.icon, info.width, &icons);
+        assert_eq!(visible_len(&icon, &icons), 2);
+        let (rain, width) = rain_icon(&icons);
+        assert_eq!(visible_len(&sized_icon(&rain, width, &icons), &icons), 2);
+
+        let colors = ThemeColors::builtin(crate::theme::Palette::Dark);
+        let opts = FrameOptions {
+            border: BorderStyle::Square,
+            min_width: 0,
+            max_width: None,
+            overflow: Overflow::Truncate,
+        };
+        let row = format!("{icon} 5°C");
+        let frame = Frame::fit(&[&row], &opts, &icons, &colors);
+        assert_eq!(frame.width, 6);
+        let line = frame.line(&row);
+        assert!(!line.contains(ICON_CELL));
+        assert!(line.contains("C 5°C "));
     }
 }
@@ -0,0 +1,132 @@
+use std::collections::BTreeMap;
+
+use crate::config::{ActionBarConfig, FontRef};
+use crate::output::{class_color, reopen_after_close, BarOutput, Renderer};
+
+/// xmobar's `<fc=#rrggbb>` tags, as printed by a `Com` or `CommandReader`.
+pub struct Xmobar {
+    class_colors: BTreeMap<String, String>,
+    actions: Vec<(u8, String)>,
+    /// Index into xmobar's `additionalFonts`
+    icon_font: u8,
+}
+
+impl Xmobar {
+    pub fn new(config: &ActionBarConfig) -> Result<Self, String> {
+        let actions = config.actions("xmobar")?;
+        if let Some((_, command)) = actions.iter().find(|(_, c)| c.contains('`')) {
+            return Err(format!(
+                "xmobar.actions: '{command}' may not contain a backtick"
+            ));
+        }
+        let icon_font = match &config.icon_font {
+            Some(FontRef::Index(n)) => *n,
+            Some(FontRef::Name(font)) => font
+                .parse()
+                .map_err(|_| format!("xmobar.icon_font: '{font}' is not a font index"))?,
+            None => 1,
+        };
+        Ok(Self {
+            class_colors: config.bar.class_colors("xmobar")?,
+            actions,
+            icon_font,
+        })
+    }
+}
+
+impl Renderer for Xmobar {
+    fn escape(&self, text: &str) -> String {
+        // xmobar has no entities; `<raw>` passes text through untouched
+        let text = text.replace('\n', " ");
+        if text.contains('<') {
+            format!("<raw={}:{text}/>", text.chars().count())
+        } else {
+            text
+        }
+    }
+
+    fn colored(&self, color: &str, text: &str) -> String {
+        format!("<fc={color}>{text}</fc>")
+    }
+
+    fn icon(&self, icon: &str, glyph: &str) -> String {
+        // Sets that wrap their glyphs in Pango (Font Awesome, custom sets with
+        // `wrap`) need a font of their own
+        if icon == glyph {
+            self.escape(glyph)
+        } else {
+            format!("<fn={}>{}</fn>", self.icon_font, self.escape(glyph))
+        }
+    }
+
+    fn emit(&self, output: &BarOutput) -> String {
+        let mut text = match class_color(&self.class_colors, &output.class) {
+            Some(color) => self.colored(color, &output.text),
+            None => output.text.clone(),
+        };
+        for (button, command) in &self.actions {
+            text = format!("<action=`{command}` button={button}>{text}</action>");
+        }
+        text
+    }
+}
+
+/// dzen2's `^fg(#rrggbb)` commands.
+pub struct Dzen2 {
+    class_colors: BTreeMap<String, String>,
+    actions: Vec<(u8, String)>,
+    icon_font: Option<String>,
+}
+
+impl Dzen2 {
+    pub fn new(config: &ActionBarConfig) -> Result<Self, String> {
+        let actions = config.actions("dzen2")?;
+        if let Some((_, command)) = actions.iter().find(|(_, c)| c.contains(')')) {
+            return Err(format!("dzen2.actions: '{command}' may not contain ')'"));
+        }
+        let icon_font = match &config.icon_font {
+            Some(FontRef::Name(font)) => Some(font.clone()),
+            Some(FontRef::Index(n)) => {
+                return Err(format!("dzen2.icon_font: {n} is not a font name"));
+            }
+            None => None,
+        };
+        Ok(Self {
+            class_colors: config.bar.class_colors("dzen2")?,
+            actions,
+            icon_font,
+        })
+    }
+}
+
+impl Renderer for Dzen2 {
+    fn escape(&self, text: &str) -> String {
+        text.replace('^', "^^").replace('\n', " ")
+    }
+
+    fn colored(&self, color: &str, text: &str) -> String {
+        format!("^fg({color}){text}^fg()")
+    }
+
+    fn icon(&self, icon: &str, glyph: &str) -> String {
+        match &self.icon_font {
+            Some(font) if icon != glyph => format!("^fn({font}){}^fn()", self.escape(glyph)),
+            _ => self.escape(glyph),
+        }
+    }
+
+    fn emit(&self, output: &BarOutput) -> String {
+        let mut text = match class_color(&self.class_colors, &output.class) {
+            Some(color) => {
+                let reopen = format!("^fg({color})");
+                let text = reopen_after_close(&output.text, '^', "^fg()", &reopen);
+                self.colored(color, &text)
+            }
+            None => output.text.clone(),
+        };
+        for (button, command) in &self.actions {
+            text = format!("^ca({button}, {command}){text}^ca()");
+        }
+        text
+    }
+}